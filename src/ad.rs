@@ -0,0 +1,219 @@
+//! Detection of ad breaks from in-band metadata.
+//!
+//! Stations signal ads in a few different ways. `AdsWizz` inserts `adw_ad='true'` along with the
+//! length of the ad in `durationMilliseconds` and its position in `insertionType`, Triton streams
+//! mark ads with `song_spot="F"`, and some stations simply clear the title or replace it with the
+//! station name. [`AdState`] checks each metadata value against a set of [`AdRules`] and reports
+//! when an ad break starts or ends.
+//!
+//! ```
+//! use icy_metadata::IcyMetadata;
+//! use icy_metadata::ad::{AdEvent, AdRules, AdState};
+//!
+//! let mut state = AdState::new(AdRules::new());
+//! let ad: IcyMetadata = "StreamTitle='';adw_ad='true';durationMilliseconds='30000';"
+//!     .parse()
+//!     .unwrap();
+//! assert!(matches!(
+//!     state.update(&ad),
+//!     Some(AdEvent::AdBreakStarted { .. })
+//! ));
+//!
+//! let song = IcyMetadata::default().with_stream_title("Artist - Title");
+//! assert_eq!(state.update(&song), Some(AdEvent::AdBreakEnded));
+//! ```
+
+use std::time::Duration;
+
+use crate::IcyMetadata;
+use crate::triton::SpotType;
+
+/// Change in the ad break state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdEvent {
+    /// An ad break started.
+    AdBreakStarted {
+        /// Length of the break, if the station sent it.
+        expected_duration: Option<Duration>,
+        /// Where the break was inserted, ex: `preroll` or `midroll`. This is only sent by
+        /// `AdsWizz`.
+        insertion_type: Option<String>,
+    },
+    /// The ad break ended.
+    AdBreakEnded,
+}
+
+/// Rules used to decide whether metadata belongs to an ad.
+///
+/// By default, `AdsWizz` and Triton markers are detected. Empty titles and titles matching the
+/// station name are ignored since many stations also send them between songs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdRules {
+    adswizz: bool,
+    triton: bool,
+    empty_title: bool,
+    station_name_title: bool,
+    custom_fields: Vec<(String, String)>,
+}
+
+impl Default for AdRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdRules {
+    /// Creates a new `AdRules` with the default settings.
+    pub fn new() -> Self {
+        Self {
+            adswizz: true,
+            triton: true,
+            empty_title: false,
+            station_name_title: false,
+            custom_fields: Vec::new(),
+        }
+    }
+
+    /// Whether to detect the `AdsWizz` `adw_ad` field. The expected duration is read from
+    /// `durationMilliseconds` and the insertion type from `insertionType`. This is enabled by
+    /// default.
+    pub fn adswizz(mut self, adswizz: bool) -> Self {
+        self.adswizz = adswizz;
+        self
+    }
+
+    /// Whether to treat a Triton `song_spot` of [`SpotType::Spot`] as an ad. The expected duration
+    /// is read from the `length` attribute. This is enabled by default.
+    pub fn triton(mut self, triton: bool) -> Self {
+        self.triton = triton;
+        self
+    }
+
+    /// Whether to treat an empty or missing title as an ad.
+    pub fn empty_title(mut self, empty_title: bool) -> Self {
+        self.empty_title = empty_title;
+        self
+    }
+
+    /// Whether to treat a title that matches the station name as an ad. The station name is set
+    /// with [`AdState::station_name`].
+    pub fn station_name_title(mut self, station_name_title: bool) -> Self {
+        self.station_name_title = station_name_title;
+        self
+    }
+
+    /// Treats metadata containing the custom field `key` with the given value as an ad. Both are
+    /// compared case-insensitively.
+    pub fn custom_field<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.custom_fields.push((key.into(), value.into()));
+        self
+    }
+
+    /// Returns the event that starts an ad break if `metadata` belongs to an ad, or `None` if it
+    /// doesn't.
+    fn detect(&self, metadata: &IcyMetadata, station_name: Option<&str>) -> Option<AdEvent> {
+        let started = |expected_duration, insertion_type| {
+            Some(AdEvent::AdBreakStarted {
+                expected_duration,
+                insertion_type,
+            })
+        };
+        let custom_field = |name: &str| {
+            metadata
+                .custom_fields()
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim())
+        };
+        if self.adswizz
+            && custom_field("adw_ad").is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            let duration = custom_field("durationMilliseconds")
+                .and_then(|millis| millis.parse().ok())
+                .map(Duration::from_millis);
+            let insertion_type = custom_field("insertionType")
+                .filter(|insertion_type| !insertion_type.is_empty())
+                .map(str::to_string);
+            return started(duration, insertion_type);
+        }
+        if let Some(triton) = metadata.triton().filter(|_| self.triton) {
+            if triton.spot_type() == Some(SpotType::Spot) {
+                return started(triton.duration(), None);
+            }
+        }
+        if self.custom_fields.iter().any(|(key, value)| {
+            custom_field(key).is_some_and(|found| found.eq_ignore_ascii_case(value))
+        }) {
+            return started(None, None);
+        }
+
+        let title = metadata.stream_title().map(str::trim).unwrap_or_default();
+        if self.empty_title && title.is_empty() {
+            return started(None, None);
+        }
+        if self.station_name_title
+            && !title.is_empty()
+            && station_name.is_some_and(|name| name.trim().eq_ignore_ascii_case(title))
+        {
+            return started(None, None);
+        }
+        None
+    }
+}
+
+/// Tracks whether a stream is in an ad break.
+///
+/// Pass each metadata value to [`Self::update`] in the order it was received. An ad break starts
+/// with the first value that matches the [`AdRules`] and ends with the first value that doesn't.
+#[derive(Clone, Debug, Default)]
+pub struct AdState {
+    rules: AdRules,
+    station_name: Option<String>,
+    in_ad_break: bool,
+}
+
+impl AdState {
+    /// Creates a new `AdState` using the given rules.
+    pub fn new(rules: AdRules) -> Self {
+        Self {
+            rules,
+            station_name: None,
+            in_ad_break: false,
+        }
+    }
+
+    /// Set the station name, as sent in the `icy-name` header. This is used by
+    /// [`AdRules::station_name_title`].
+    pub fn station_name<S>(mut self, station_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.station_name = Some(station_name.into());
+        self
+    }
+
+    /// Whether an ad break is currently airing.
+    pub fn in_ad_break(&self) -> bool {
+        self.in_ad_break
+    }
+
+    /// Processes the next metadata value, returning an event if an ad break started or ended.
+    pub fn update(&mut self, metadata: &IcyMetadata) -> Option<AdEvent> {
+        let detected = self.rules.detect(metadata, self.station_name.as_deref());
+        match (detected, self.in_ad_break) {
+            (Some(started), false) => {
+                self.in_ad_break = true;
+                Some(started)
+            }
+            (None, true) => {
+                self.in_ad_break = false;
+                Some(AdEvent::AdBreakEnded)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Client for updating in-band metadata through a server's admin interface.
+//!
+//! Icecast exposes this through `/admin/metadata` and Shoutcast through `/admin.cgi`. In both
+//! cases, the server inserts the new values into the metadata blocks of MP3 and AAC mounts.
+
+use reqwest::{Client, StatusCode, Url};
+
+use crate::IcyMetadata;
+use crate::error::AdminError;
+use crate::xml::Element;
+
+/// Server implementation that will receive the update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerKind {
+    /// Icecast server. The update is sent to the `mount`.
+    Icecast {
+        /// Mount point to update, ex: `/live`.
+        mount: String,
+    },
+    /// Shoutcast server. The update is sent to the stream with the given `sid`.
+    Shoutcast {
+        /// Stream ID. Shoutcast v1 servers only have a single stream, so this can be left as
+        /// `None`.
+        sid: Option<u32>,
+    },
+}
+
+/// Character set used to encode the metadata values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetadataCharset {
+    /// UTF-8
+    #[default]
+    Utf8,
+    /// ISO-8859-1. Characters that can't be represented are replaced with `?`.
+    Latin1,
+}
+
+impl MetadataCharset {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    fn encode(&self, val: &str) -> String {
+        match self {
+            Self::Utf8 => urlencoding::encode(val).into_owned(),
+            Self::Latin1 => {
+                let bytes: Vec<u8> = val
+                    .chars()
+                    .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                    .collect();
+                urlencoding::encode_binary(&bytes).into_owned()
+            }
+        }
+    }
+}
+
+/// Response returned after a successful metadata update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateResponse {
+    message: Option<String>,
+}
+
+impl UpdateResponse {
+    /// Message returned by the server, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// Client for sending metadata updates to an Icecast or Shoutcast server.
+#[derive(Clone, Debug)]
+pub struct AdminClient {
+    client: Client,
+    base_url: Url,
+    server_kind: ServerKind,
+    username: String,
+    password: String,
+    charset: MetadataCharset,
+}
+
+impl AdminClient {
+    /// Creates a new `AdminClient` for the server located at `base_url`. If the server is behind a
+    /// reverse proxy, `base_url` can include a path prefix, ex: `https://example.com/radio`.
+    /// Defaults to the `admin` user with an empty password.
+    pub fn new(base_url: Url, server_kind: ServerKind) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+            server_kind,
+            username: "admin".to_string(),
+            password: String::new(),
+            charset: MetadataCharset::default(),
+        }
+    }
+
+    /// Set the HTTP client used to send requests.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Set the credentials used to authenticate with the server. Shoutcast only uses the password.
+    pub fn credentials<U, P>(mut self, username: U, password: P) -> Self
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.username = username.into();
+        self.password = password.into();
+        self
+    }
+
+    /// Set the character set used to encode the metadata values.
+    pub fn charset(mut self, charset: MetadataCharset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Builds the URL used to update the metadata.
+    pub fn update_url(&self, metadata: &IcyMetadata) -> Url {
+        let song = self
+            .charset
+            .encode(metadata.stream_title().unwrap_or_default());
+        let mut url = self.base_url.clone();
+        // Build the query manually since the server expects the values to be encoded with the
+        // requested charset, which the URL serializer doesn't support
+        let query = match &self.server_kind {
+            ServerKind::Icecast { mount } => {
+                set_admin_path(&mut url, "admin/metadata");
+                format!(
+                    "mount={}&mode=updinfo&song={song}&charset={}",
+                    urlencoding::encode(mount),
+                    self.charset.name()
+                )
+            }
+            ServerKind::Shoutcast { sid } => {
+                set_admin_path(&mut url, "admin.cgi");
+                let mut query = format!(
+                    "pass={}&mode=updinfo&song={song}",
+                    urlencoding::encode(&self.password)
+                );
+                if let Some(sid) = sid {
+                    query.push_str(&format!("&sid={sid}"));
+                }
+                if let Some(stream_url) = metadata.stream_url() {
+                    query.push_str(&format!("&url={}", self.charset.encode(stream_url)));
+                }
+                query
+            }
+        };
+        url.set_query(Some(&query));
+        url
+    }
+
+    /// Sends the metadata update to the server.
+    pub async fn update_metadata(
+        &self,
+        metadata: &IcyMetadata,
+    ) -> Result<UpdateResponse, AdminError> {
+        let mut request = self.client.get(self.update_url(metadata));
+        if matches!(self.server_kind, ServerKind::Icecast { .. }) {
+            request = request.basic_auth(&self.username, Some(&self.password));
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(AdminError::Unauthorized),
+            status if status.is_success() => parse_update_response(&body),
+            status => Err(AdminError::UnexpectedStatus {
+                status: status.as_u16(),
+                body,
+            }),
+        }
+    }
+}
+
+fn parse_update_response(body: &str) -> Result<UpdateResponse, AdminError> {
+    // Icecast responds with an XML document like
+    // <iceresponse><message>Metadata update successful</message><return>1</return></iceresponse>.
+    // Shoutcast doesn't send anything useful, so a successful status is all we have to go on.
+    let Some(response) = Element::parse(body)
+        .ok()
+        .filter(|root| root.name.eq_ignore_ascii_case("iceresponse"))
+    else {
+        return Ok(UpdateResponse { message: None });
+    };
+    let message = response.child_text("message").map(ToString::to_string);
+    if response.child_text("return") == Some("1") {
+        Ok(UpdateResponse { message })
+    } else {
+        Err(AdminError::Rejected(message.unwrap_or_default()))
+    }
+}
+
+/// Appends `path` to the path of the base URL so any prefix is kept.
+fn set_admin_path(url: &mut Url, path: &str) {
+    let base = url.path().trim_end_matches('/').to_string();
+    url.set_path(&format!("{base}/{path}"));
+}
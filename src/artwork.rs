@@ -0,0 +1,58 @@
+/// Image embedded within a stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Artwork {
+    kind: ArtworkKind,
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+/// What an [`Artwork`] image represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArtworkKind {
+    /// Logo or other artwork for the station itself.
+    Station,
+    /// Artwork for the currently playing track, such as the album cover.
+    Track,
+}
+
+impl ArtworkKind {
+    /// Maps the picture type used by ID3 `APIC` frames and FLAC picture blocks.
+    pub(crate) fn from_picture_type(picture_type: u32) -> Self {
+        match picture_type {
+            // file icons and publisher logos
+            1 | 2 | 20 => Self::Station,
+            _ => Self::Track,
+        }
+    }
+}
+
+impl Artwork {
+    /// Creates a new `Artwork`.
+    pub fn new<S>(kind: ArtworkKind, mime_type: S, data: Vec<u8>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            kind,
+            mime_type: mime_type.into(),
+            data,
+        }
+    }
+
+    /// What the image represents.
+    pub fn kind(&self) -> ArtworkKind {
+        self.kind
+    }
+
+    /// MIME type of the image, ex: `image/jpeg`.
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// Raw image bytes.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
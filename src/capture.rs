@@ -0,0 +1,334 @@
+//! Capture files for recording and replaying raw streams.
+//!
+//! A capture stores the raw response status line and headers followed by the raw response body,
+//! including any icy metadata blocks, along with the time each chunk of the body arrived. This
+//! makes it possible to reproduce parsing issues for a specific station without a live connection.
+//!
+//! The format starts with the magic bytes `ICYCAP1\n` and the response head exactly as it was
+//! received. The rest of the file is a sequence of chunks, each made up of the arrival time
+//! in microseconds since the start of the capture as a big-endian `u64`, the chunk length as a
+//! big-endian `u32`, and the chunk data.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::{self, BufReader, BufWriter, Read};
+//!
+//! use icy_metadata::IcyMetadataReader;
+//! use icy_metadata::capture::{CaptureReplay, CaptureTap, CaptureWriter};
+//! use icy_metadata::client::IcyClient;
+//!
+//! # fn main() -> io::Result<()> {
+//! let response = IcyClient::new("example.com", 8000, "/;").connect()?;
+//! let writer = CaptureWriter::new(
+//!     BufWriter::new(File::create("station.icycap")?),
+//!     response.raw_head(),
+//! )?;
+//! let mut tap = CaptureTap::new(response.into_inner(), writer);
+//! io::copy(&mut (&mut tap).take(1024 * 1024), &mut io::sink())?;
+//! drop(tap);
+//!
+//! let replay = CaptureReplay::new(BufReader::new(File::open("station.icycap")?))?;
+//! let icy_headers = replay.icy_headers();
+//! let reader = IcyMetadataReader::new(replay, icy_headers.metadata_interval(), |metadata| {
+//!     println!("{metadata:?}");
+//! });
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Read, Write};
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use http::{HeaderMap, StatusCode};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::IcyHeaders;
+use crate::http_head::{ResponseHead, read_response_head};
+
+const MAGIC: &[u8] = b"ICYCAP1\n";
+// Arrival time (8) + chunk length (4)
+const CHUNK_HEADER_LEN: usize = 12;
+// Largest gap between chunks accepted when replaying in realtime. A stalled connection would
+// normally time out long before this.
+const MAX_CHUNK_GAP: Duration = Duration::from_secs(60);
+
+/// Writes a capture file.
+#[derive(Debug)]
+pub struct CaptureWriter<W> {
+    inner: W,
+    start: Instant,
+}
+
+impl<W> CaptureWriter<W>
+where
+    W: Write,
+{
+    /// Creates a new `CaptureWriter` and writes the response head. `head` is the status line and
+    /// headers exactly as they were received, including the blank line at the end, such as
+    /// [`IcyResponse::raw_head`](crate::client::IcyResponse::raw_head). Chunk arrival times are
+    /// measured from when this is called.
+    pub fn new(mut inner: W, head: &[u8]) -> io::Result<Self> {
+        // Make sure the capture can be read back before writing anything
+        let mut rest = head;
+        match read_response_head(&mut rest) {
+            Ok(_) if rest.is_empty() => {}
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "response head must not be followed by any other data",
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "response head must end with a blank line",
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+
+        inner.write_all(MAGIC)?;
+        inner.write_all(head)?;
+        Ok(Self {
+            inner,
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends a chunk of the response body, timestamped with the current time. Empty chunks are
+    /// ignored.
+    pub fn write_chunk(&mut self, data: &[u8]) -> io::Result<()> {
+        let elapsed = u64::try_from(self.start.elapsed().as_micros()).unwrap_or(u64::MAX);
+        for chunk in data.chunks(u32::MAX as usize) {
+            self.inner.write_all(&elapsed.to_be_bytes())?;
+            self.inner.write_all(&(chunk.len() as u32).to_be_bytes())?;
+            self.inner.write_all(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Consumes the `CaptureWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reader that passes data through unchanged while appending everything it reads to a capture.
+///
+/// Implements [`Read`], and `AsyncRead` when the `tokio` feature is enabled. Writes to the capture
+/// are blocking in both cases, so a buffered writer should be used.
+#[derive(Debug)]
+pub struct CaptureTap<R, W> {
+    inner: R,
+    writer: CaptureWriter<W>,
+}
+
+impl<R, W> CaptureTap<R, W>
+where
+    W: Write,
+{
+    /// Creates a new `CaptureTap` that records the data read from `inner`.
+    pub fn new(inner: R, writer: CaptureWriter<W>) -> Self {
+        Self { inner, writer }
+    }
+
+    /// Consumes the `CaptureTap`, returning the reader and the capture writer.
+    pub fn into_parts(self) -> (R, CaptureWriter<W>) {
+        (self.inner, self.writer)
+    }
+}
+
+impl<R, W> Read for CaptureTap<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.writer.write_chunk(&buf[..read])?;
+        Ok(read)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R, W> AsyncRead for CaptureTap<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: Write + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let start = buf.filled().len();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(this.writer.write_chunk(&buf.filled()[start..])),
+            poll => poll,
+        }
+    }
+}
+
+/// Replays a capture file.
+///
+/// The response body is served through the [`Read`] implementation. By default, it's returned
+/// as fast as it can be read. Use [`Self::realtime`] to wait until each chunk's original arrival
+/// time before returning it.
+#[derive(Debug)]
+pub struct CaptureReplay<R> {
+    inner: R,
+    head: ResponseHead,
+    realtime: bool,
+    start: Option<Instant>,
+    last_time: Duration,
+    remaining: u32,
+}
+
+impl<R> CaptureReplay<R>
+where
+    R: Read,
+{
+    /// Creates a new `CaptureReplay` and reads the response head from the capture.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        inner.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an icy capture file",
+            ));
+        }
+        let head = read_response_head(&mut inner)?;
+        Ok(Self {
+            inner,
+            head,
+            realtime: false,
+            start: None,
+            last_time: Duration::ZERO,
+            remaining: 0,
+        })
+    }
+
+    /// Whether to return each chunk at the time it originally arrived, relative to the first read.
+    /// Reads fail with [`io::ErrorKind::InvalidData`] if a chunk's arrival time is earlier than
+    /// the previous chunk or more than a minute after it.
+    pub fn realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Captured response status code.
+    pub fn status(&self) -> StatusCode {
+        self.head.status
+    }
+
+    /// Reason phrase sent with the status code.
+    pub fn reason(&self) -> &str {
+        &self.head.reason
+    }
+
+    /// Whether the captured response had an `ICY` status line instead of an HTTP one.
+    pub fn is_icy(&self) -> bool {
+        self.head.protocol == "ICY"
+    }
+
+    /// Captured response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.head.headers
+    }
+
+    /// The captured status line and headers exactly as they were received, including the blank
+    /// line at the end.
+    pub fn raw_head(&self) -> &[u8] {
+        &self.head.raw
+    }
+
+    /// Parses the icy metadata contained in the captured response headers.
+    pub fn icy_headers(&self) -> IcyHeaders {
+        IcyHeaders::parse_from_headers(&self.head.headers)
+    }
+
+    /// Consumes the `CaptureReplay`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the next chunk header. Returns `false` at the end of the capture.
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        let mut header = [0; CHUNK_HEADER_LEN];
+        let mut filled = 0;
+        while filled < CHUNK_HEADER_LEN {
+            match self.inner.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "capture ended in the middle of a chunk header",
+                    ));
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let (time, len) = header.split_at(8);
+        let time = Duration::from_micros(u64::from_be_bytes(time.try_into().unwrap_or_default()));
+        self.remaining = u32::from_be_bytes(len.try_into().unwrap_or_default());
+
+        let start = *self.start.get_or_insert_with(Instant::now);
+        if self.realtime {
+            if time < self.last_time || time - self.last_time > MAX_CHUNK_GAP {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "chunk arrival time {time:?} is out of order, previous chunk arrived at \
+                         {:?}",
+                        self.last_time
+                    ),
+                ));
+            }
+            self.last_time = time;
+            if let Some(wait) = (start + time).checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R> Read for CaptureReplay<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.remaining == 0 {
+            if !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.remaining as usize);
+        let read = self.inner.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "capture ended in the middle of a chunk",
+            ));
+        }
+        self.remaining -= read as u32;
+        Ok(read)
+    }
+}
@@ -0,0 +1,214 @@
+//! Minimal client for servers that can't be reached with a standard HTTP client.
+//!
+//! Shoutcast v1 servers respond with an `ICY 200 OK` status line instead of `HTTP/1.0 200 OK`,
+//! which most HTTP clients reject outright. This client sends a plain `GET` request over a raw TCP
+//! connection and accepts either status line.
+//!
+//! ```no_run
+//! use icy_metadata::IcyMetadataReader;
+//! use icy_metadata::client::IcyClient;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let response = IcyClient::new("example.com", 8000, "/;").connect()?;
+//! let icy_headers = response.icy_headers();
+//! let reader = IcyMetadataReader::new(
+//!     response.into_inner(),
+//!     icy_headers.metadata_interval(),
+//!     |metadata| println!("{metadata:?}"),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Write};
+use std::net::TcpStream;
+
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Uri, header};
+
+use crate::http_head::{ResponseHead, basic_auth, read_response_head, write_request_head};
+use crate::{ICY_METADATA_HEADER, IcyHeaders};
+
+/// Client that requests a stream with icy metadata over a raw TCP connection.
+#[derive(Clone, Debug)]
+pub struct IcyClient {
+    host: String,
+    port: u16,
+    path: String,
+    headers: HeaderMap,
+}
+
+impl IcyClient {
+    /// Creates a new `IcyClient` that will request `path` from the given server. IPv6 addresses
+    /// can be given with or without brackets.
+    pub fn new<H, P>(host: H, port: u16, path: P) -> Self
+    where
+        H: Into<String>,
+        P: Into<String>,
+    {
+        let host = host.into();
+        // Brackets are only part of the URI syntax, the address itself can't contain them
+        let host = match host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+        {
+            Some(address) => address.to_string(),
+            None => host,
+        };
+        let path = path.into();
+        let path = if path.starts_with('/') {
+            path
+        } else {
+            format!("/{path}")
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ICY_METADATA_HEADER, HeaderValue::from_static("1"));
+        let host_header = if host.contains(':') {
+            format!("[{host}]")
+        } else {
+            host.clone()
+        };
+        let host_header = if port == 80 {
+            host_header
+        } else {
+            format!("{host_header}:{port}")
+        };
+        if let Ok(host_header) = HeaderValue::from_str(&host_header) {
+            headers.insert(header::HOST, host_header);
+        }
+        headers.insert(
+            header::USER_AGENT,
+            HeaderValue::from_static(concat!("icy-metadata/", env!("CARGO_PKG_VERSION"))),
+        );
+        Self {
+            host,
+            port,
+            path,
+            headers,
+        }
+    }
+
+    /// Creates a new `IcyClient` from an `http://` URI. The port defaults to `80` if it isn't
+    /// specified.
+    pub fn from_uri(uri: &Uri) -> io::Result<Self> {
+        if uri.scheme_str().is_some_and(|scheme| scheme != "http") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only http URIs are supported",
+            ));
+        }
+        let host = uri
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URI is missing a host"))?;
+        let path = uri.path_and_query().map_or("/", |path| path.as_str());
+        Ok(Self::new(host, uri.port_u16().unwrap_or(80), path))
+    }
+
+    /// Adds a header to the request. Replaces any existing value with the same name.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set the credentials used to authenticate with the server.
+    pub fn credentials<U, P>(mut self, username: U, password: P) -> Self
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        let value = basic_auth(&username.into(), &password.into());
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            self.headers.insert(header::AUTHORIZATION, value);
+        }
+        self
+    }
+
+    /// Whether the request will ask the server for icy metadata. This is enabled by default.
+    pub fn request_icy_metadata(mut self, request: bool) -> Self {
+        if request {
+            self.headers
+                .insert(ICY_METADATA_HEADER, HeaderValue::from_static("1"));
+        } else {
+            self.headers.remove(ICY_METADATA_HEADER);
+        }
+        self
+    }
+
+    fn request_head(&self) -> Vec<u8> {
+        write_request_head("GET", &self.path, "HTTP/1.0", &self.headers)
+    }
+
+    /// Connects to the server and reads the response head. The returned [`IcyResponse`] contains
+    /// the connection, positioned at the start of the response body.
+    pub fn connect(&self) -> io::Result<IcyResponse<TcpStream>> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.write_all(&self.request_head())?;
+        stream.flush()?;
+        let head = read_response_head(&mut stream)?;
+        Ok(IcyResponse::new(head, stream))
+    }
+
+    /// Async version of [`Self::connect`].
+    #[cfg(feature = "tokio")]
+    pub async fn connect_async(&self) -> io::Result<IcyResponse<tokio::net::TcpStream>> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut stream = tokio::net::TcpStream::connect((self.host.as_str(), self.port)).await?;
+        stream.write_all(&self.request_head()).await?;
+        stream.flush().await?;
+        let head = crate::http_head::read_response_head_async(&mut stream).await?;
+        Ok(IcyResponse::new(head, stream))
+    }
+}
+
+/// Response returned from [`IcyClient`].
+#[derive(Debug)]
+pub struct IcyResponse<S> {
+    head: ResponseHead,
+    inner: S,
+}
+
+impl<S> IcyResponse<S> {
+    fn new(head: ResponseHead, inner: S) -> Self {
+        Self { head, inner }
+    }
+
+    /// Response status code.
+    pub fn status(&self) -> StatusCode {
+        self.head.status
+    }
+
+    /// Reason phrase sent with the status code.
+    pub fn reason(&self) -> &str {
+        &self.head.reason
+    }
+
+    /// Whether the server responded with an `ICY` status line instead of an HTTP one.
+    pub fn is_icy(&self) -> bool {
+        self.head.protocol == "ICY"
+    }
+
+    /// Response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.head.headers
+    }
+
+    /// Parses the icy metadata contained in the response headers.
+    pub fn icy_headers(&self) -> IcyHeaders {
+        IcyHeaders::parse_from_headers(&self.head.headers)
+    }
+
+    /// The status line and headers exactly as they were received, including the blank line at the
+    /// end. Unlike [`Self::headers`], this preserves header order, casing, and any lines that
+    /// couldn't be parsed.
+    pub fn raw_head(&self) -> &[u8] {
+        &self.head.raw
+    }
+
+    /// Consumes the response, returning the connection. The connection is positioned at the start
+    /// of the response body, so it can be passed directly to
+    /// [`IcyMetadataReader::new`](crate::IcyMetadataReader::new).
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
@@ -0,0 +1,265 @@
+//! Cue sheets for continuous recordings.
+//!
+//! A [`CueSheet`] describes where each track starts within a single recorded file, allowing
+//! players to seek between tracks without splitting the recording. Track start times are
+//! calculated from the byte offset where the metadata changed.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::frame::FrameAligner;
+use crate::recorder::{sanitize, truncate_file_name};
+use crate::{IcyHeaders, IcyMetadata};
+
+// Cue sheet timestamps are measured in CD frames
+const FRAMES_PER_SECOND: u128 = 75;
+// The cue sheet format only allows track numbers from 1 to 99
+const MAX_TRACKS: usize = 99;
+// Number of frame boundaries to keep for tracks that are added after their audio was processed
+const MAX_BOUNDARY_HISTORY: usize = 4096;
+
+/// Track within a [`CueSheet`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CueTrack {
+    offset: u64,
+    start: Option<Duration>,
+    title: Option<String>,
+    performer: Option<String>,
+}
+
+impl CueTrack {
+    /// Byte offset of the start of the track within the recorded audio.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Start time of the track. This is `None` if the track is waiting for audio to calculate a
+    /// frame-accurate start time.
+    pub fn start(&self) -> Option<Duration> {
+        self.start
+    }
+
+    /// Track title.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Track artist.
+    pub fn performer(&self) -> Option<&str> {
+        self.performer.as_deref()
+    }
+}
+
+/// Cue sheet for a single continuous recording.
+///
+/// Tracks are added with [`Self::add_track`] using the number of audio bytes that were recorded
+/// before the metadata changed. By default, the offset is converted to a time using the stream
+/// bitrate from the [`IcyHeaders`]. This is only an estimate for VBR streams. For frame-accurate
+/// timestamps, pass the recorded audio to [`Self::push_audio`] and the start of each track will
+/// be moved to the nearest MP3 or AAC frame.
+#[derive(Clone, Debug)]
+pub struct CueSheet {
+    title: Option<String>,
+    file: String,
+    file_type: String,
+    bytes_per_second: Option<u64>,
+    clock: Option<FrameClock>,
+    tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// Creates a new `CueSheet`. The title is taken from the station name and the file name
+    /// defaults to the station name with an `.mp3` extension.
+    pub fn new(headers: &IcyHeaders) -> Self {
+        Self {
+            title: headers.name().map(str::to_string),
+            file: truncate_file_name(&format!(
+                "{}.mp3",
+                sanitize(headers.name().unwrap_or_default())
+            )),
+            file_type: "MP3".to_string(),
+            bytes_per_second: headers
+                .bitrate()
+                .map(|bitrate| u64::from(bitrate) * 1000 / 8),
+            clock: None,
+            tracks: Vec::new(),
+        }
+    }
+
+    /// Set the name of the recorded file and its type, ex: `MP3` or `WAVE`.
+    pub fn file<F, T>(mut self, file: F, file_type: T) -> Self
+    where
+        F: Into<String>,
+        T: Into<String>,
+    {
+        self.file = file.into();
+        self.file_type = file_type.into();
+        self
+    }
+
+    /// Adds a track that starts `offset` bytes into the recorded audio. The performer and title
+    /// are taken from the metadata. If they weren't sent in a structured format, they're parsed
+    /// from a `StreamTitle` in the form `Artist - Title`.
+    pub fn add_track(&mut self, offset: u64, metadata: &IcyMetadata) {
+        let (performer, title) = metadata.artist_and_title();
+        let start = match &self.clock {
+            // Older audio may have been dropped from the history, fall back to the average rate
+            Some(clock) if offset <= clock.bytes => {
+                clock.time_at(offset).or_else(|| clock.average_time(offset))
+            }
+            Some(_) => None,
+            None => self.estimate(offset),
+        };
+        self.tracks.push(CueTrack {
+            offset,
+            start,
+            title: title.map(str::to_string),
+            performer: performer.map(str::to_string),
+        });
+    }
+
+    /// Processes recorded audio so track start times can be calculated from the frame durations.
+    /// The audio must be passed in order, starting from the beginning of the recording.
+    pub fn push_audio(&mut self, data: &[u8]) {
+        let clock = self.clock.get_or_insert_with(FrameClock::default);
+        clock.push(data);
+        for track in self.tracks.iter_mut().filter(|track| track.start.is_none()) {
+            track.start = clock.time_at(track.offset);
+        }
+    }
+
+    /// Tracks in the cue sheet.
+    pub fn tracks(&self) -> &[CueTrack] {
+        &self.tracks
+    }
+
+    /// Writes the cue sheet to `writer`. Cue sheets can contain at most 99 tracks, an error is
+    /// returned if more were added.
+    pub fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        if self.tracks.len() > MAX_TRACKS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "cue sheet has {} tracks, the maximum is {MAX_TRACKS}",
+                    self.tracks.len()
+                ),
+            ));
+        }
+        write!(writer, "{self}")
+    }
+
+    fn estimate(&self, offset: u64) -> Option<Duration> {
+        let bytes_per_second = self.bytes_per_second.filter(|bytes| *bytes > 0)?;
+        Some(Duration::from_secs_f64(
+            offset as f64 / bytes_per_second as f64,
+        ))
+    }
+
+    fn start_time(&self, track: &CueTrack) -> Duration {
+        // If the audio for this track hasn't been processed yet, fall back to the average rate of
+        // the audio that has
+        track
+            .start
+            .or_else(|| self.clock.as_ref()?.average_time(track.offset))
+            .or_else(|| self.estimate(track.offset))
+            .unwrap_or_default()
+    }
+}
+
+/// Only the first 99 tracks are written, use [`CueSheet::write_to`] to detect when tracks would be
+/// left out.
+impl Display for CueSheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "TITLE {}", quote(title))?;
+        }
+        writeln!(f, "FILE {} {}", quote(&self.file), self.file_type)?;
+        for (i, track) in self.tracks.iter().take(MAX_TRACKS).enumerate() {
+            writeln!(f, "  TRACK {:02} AUDIO", i + 1)?;
+            if let Some(title) = &track.title {
+                writeln!(f, "    TITLE {}", quote(title))?;
+            }
+            if let Some(performer) = &track.performer {
+                writeln!(f, "    PERFORMER {}", quote(performer))?;
+            }
+            writeln!(f, "    INDEX 01 {}", timestamp(self.start_time(track)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Tracks the elapsed time at each frame boundary.
+#[derive(Clone, Debug, Default)]
+struct FrameClock {
+    aligner: FrameAligner,
+    bytes: u64,
+    elapsed: Duration,
+    boundaries: VecDeque<(u64, Duration)>,
+}
+
+impl FrameClock {
+    fn push(&mut self, data: &[u8]) {
+        self.aligner.push(data);
+        while let Some((header, unit)) = self.aligner.next(false, usize::MAX) {
+            if let Some(header) = header {
+                if self.boundaries.len() == MAX_BOUNDARY_HISTORY {
+                    self.boundaries.pop_front();
+                }
+                self.boundaries.push_back((self.bytes, self.elapsed));
+                self.elapsed += header.duration();
+            }
+            self.bytes += unit.len() as u64;
+        }
+    }
+
+    /// Time at the frame boundary nearest to `offset`, or `None` if the audio at `offset` isn't
+    /// in the processed history.
+    fn time_at(&self, offset: u64) -> Option<Duration> {
+        let (first, _) = self.boundaries.front()?;
+        if offset < *first || offset > self.bytes {
+            return None;
+        }
+        let end = (self.bytes, self.elapsed);
+        let next = self
+            .boundaries
+            .partition_point(|(start, _)| *start < offset);
+        let after = self.boundaries.get(next).copied().unwrap_or(end);
+        let before = next
+            .checked_sub(1)
+            .and_then(|i| self.boundaries.get(i))
+            .copied()
+            .unwrap_or(after);
+        if offset - before.0 <= after.0 - offset {
+            Some(before.1)
+        } else {
+            Some(after.1)
+        }
+    }
+
+    fn average_time(&self, offset: u64) -> Option<Duration> {
+        let rate = self.bytes as f64 / self.elapsed.as_secs_f64();
+        (rate.is_finite() && rate > 0.0).then(|| Duration::from_secs_f64(offset as f64 / rate))
+    }
+}
+
+/// Formats a duration as `MM:SS:FF`, where `FF` is the number of CD frames.
+fn timestamp(time: Duration) -> String {
+    let frames = time.as_millis() * FRAMES_PER_SECOND / 1000;
+    let seconds = frames / FRAMES_PER_SECOND;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 60,
+        seconds % 60,
+        frames % FRAMES_PER_SECOND
+    )
+}
+
+fn quote(value: &str) -> String {
+    // Cue sheets don't support escaping quotes
+    format!("\"{}\"", value.replace('"', "'"))
+}
@@ -1,24 +1,34 @@
-//! Errors returned from parsing icy metadata.
+//! Errors returned from parsing icy metadata and communicating with streaming servers.
 
 use std::error::Error;
 use std::fmt::Display;
+use std::io;
 use std::string::FromUtf8Error;
 
 /// Error returned when parsing metadata from a stream fails.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum MetadataParseError {
     /// Metadata block contained invalid UTF-8 data.
     InvalidUtf8(FromUtf8Error),
     /// Metadata block contained no valid values.
     Empty(EmptyMetadataError),
+    /// Metadata block was expected to contain XML, but it couldn't be parsed.
+    InvalidXml(InvalidXmlError),
+    /// Metadata was expected to contain an ID3 tag, but it couldn't be parsed.
+    InvalidId3(InvalidId3Error),
 }
 
 impl Display for MetadataParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(
-            "Failed to parse icy metadata block as a string. The stream may not be properly \
-             encoded.",
-        )
+        match self {
+            Self::InvalidXml(e) => write!(f, "Failed to parse XML metadata block: {e}"),
+            Self::InvalidId3(e) => write!(f, "Failed to parse ID3 tag: {e}"),
+            Self::InvalidUtf8(_) | Self::Empty(_) => f.write_str(
+                "Failed to parse icy metadata block as a string. The stream may not be properly \
+                 encoded.",
+            ),
+        }
     }
 }
 
@@ -35,3 +45,216 @@ impl Display for EmptyMetadataError {
 }
 
 impl Error for EmptyMetadataError {}
+
+/// Error returned when an Icecast source connection fails.
+#[derive(Debug)]
+pub enum SourceError {
+    /// An I/O error occurred while communicating with the server.
+    Io(io::Error),
+    /// The server rejected the supplied credentials.
+    Unauthorized,
+    /// The server refused the source. Icecast uses this when the mount point is already in use or
+    /// the content type isn't supported.
+    Forbidden(String),
+    /// The server doesn't support the request method.
+    MethodNotAllowed,
+    /// The server responded with an unexpected status.
+    UnexpectedStatus {
+        /// Response status code.
+        status: u16,
+        /// Reason phrase sent with the status.
+        reason: String,
+    },
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error while communicating with the server: {e}"),
+            Self::Unauthorized => f.write_str("The server rejected the source credentials"),
+            Self::Forbidden(reason) => write!(f, "The server refused the source: {reason}"),
+            Self::MethodNotAllowed => f.write_str("The server does not support the request method"),
+            Self::UnexpectedStatus { status, reason } => {
+                write!(f, "Unexpected response status {status} {reason}")
+            }
+        }
+    }
+}
+
+impl Error for SourceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SourceError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Error returned when an XML document can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidXmlError(pub String);
+
+impl Display for InvalidXmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid XML document: {}", self.0)
+    }
+}
+
+impl Error for InvalidXmlError {}
+
+/// Error returned when an ID3 tag can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidId3Error(pub String);
+
+impl Display for InvalidId3Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid ID3 tag: {}", self.0)
+    }
+}
+
+impl Error for InvalidId3Error {}
+
+/// Error returned when a playlist can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidPlaylistError(pub String);
+
+impl Display for InvalidPlaylistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid playlist: {}", self.0)
+    }
+}
+
+impl Error for InvalidPlaylistError {}
+
+/// Error returned when a server status document can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidStatusError(pub String);
+
+impl Display for InvalidStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid server status: {}", self.0)
+    }
+}
+
+impl Error for InvalidStatusError {}
+
+/// Error returned when a metadata timeline can't be imported.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTimelineError(pub String);
+
+#[cfg(feature = "serde")]
+impl Display for InvalidTimelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid metadata timeline: {}", self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for InvalidTimelineError {}
+
+/// Error returned when a server admin request fails.
+#[cfg(feature = "reqwest")]
+#[derive(Debug)]
+pub enum AdminError {
+    /// The HTTP request failed.
+    Request(reqwest::Error),
+    /// The server rejected the supplied credentials.
+    Unauthorized,
+    /// The server processed the request but reported a failure.
+    Rejected(String),
+    /// The server responded with an unexpected status.
+    UnexpectedStatus {
+        /// Response status code.
+        status: u16,
+        /// Response body.
+        body: String,
+    },
+}
+
+#[cfg(feature = "reqwest")]
+impl Display for AdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "Admin request failed: {e}"),
+            Self::Unauthorized => f.write_str("The server rejected the admin credentials"),
+            Self::Rejected(message) => write!(f, "The server rejected the request: {message}"),
+            Self::UnexpectedStatus { status, body } => {
+                write!(f, "Unexpected response status {status}: {body}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Error for AdminError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for AdminError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Request(value)
+    }
+}
+
+/// Error returned when a YP directory request fails.
+#[cfg(feature = "reqwest")]
+#[derive(Debug)]
+pub enum YpError {
+    /// The HTTP request failed.
+    Request(reqwest::Error),
+    /// The stream hasn't been added to the directory yet, so there's no SID to update.
+    NotListed,
+    /// The directory processed the request but reported a failure.
+    Rejected(String),
+    /// The directory responded with an unexpected status.
+    UnexpectedStatus {
+        /// Response status code.
+        status: u16,
+        /// Response body.
+        body: String,
+    },
+}
+
+#[cfg(feature = "reqwest")]
+impl Display for YpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "YP request failed: {e}"),
+            Self::NotListed => f.write_str("The stream hasn't been added to the directory"),
+            Self::Rejected(message) => write!(f, "The directory rejected the request: {message}"),
+            Self::UnexpectedStatus { status, body } => {
+                write!(f, "Unexpected response status {status}: {body}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Error for YpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for YpError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Request(value)
+    }
+}
@@ -0,0 +1,296 @@
+//! Frame parsing for MPEG audio and AAC ADTS streams.
+//!
+//! Icy metadata blocks are inserted at fixed byte offsets, so they rarely line up with the audio
+//! frames. [`FrameHeader`] is used to find the frame boundaries when a stream needs to be split.
+
+use std::time::Duration;
+
+const MPEG_HEADER_LEN: usize = 4;
+const ADTS_HEADER_LEN: usize = 7;
+// Longest header we need to see before deciding that a sync word isn't a frame
+const MAX_HEADER_LEN: usize = ADTS_HEADER_LEN;
+const ADTS_SAMPLES_PER_FRAME: u32 = 1024;
+const ADTS_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+// Bitrates in kbps, indexed by the bitrate field. Index 0 is a free format stream, which we don't
+// support since the frame length can't be calculated from the header.
+const MPEG1_LAYER1_BITRATES: [u32; 15] = [
+    0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+];
+const MPEG1_LAYER2_BITRATES: [u32; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+];
+const MPEG1_LAYER3_BITRATES: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const MPEG2_LAYER1_BITRATES: [u32; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+];
+const MPEG2_LAYER2_BITRATES: [u32; 15] =
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// Container format of an audio frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    /// MPEG audio, ex: MP3.
+    Mpeg,
+    /// AAC with ADTS headers.
+    Adts,
+}
+
+/// Header of an MPEG audio or ADTS frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    kind: FrameKind,
+    raw: [u8; 4],
+    len: usize,
+    sample_rate: u32,
+    samples: u32,
+    bitrate: Option<u32>,
+}
+
+impl FrameHeader {
+    /// Parses a frame header from the start of `buf`. Returns `None` if `buf` doesn't start with a
+    /// valid header.
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        Self::parse_mpeg(buf).or_else(|| Self::parse_adts(buf))
+    }
+
+    fn parse_mpeg(buf: &[u8]) -> Option<Self> {
+        let header: [u8; MPEG_HEADER_LEN] = buf.get(..MPEG_HEADER_LEN)?.try_into().ok()?;
+        // 11 bit sync word
+        if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        // 0 = MPEG 2.5, 1 = reserved, 2 = MPEG 2, 3 = MPEG 1
+        let version = (header[1] >> 3) & 0x03;
+        // 0 = reserved, 1 = layer III, 2 = layer II, 3 = layer I
+        let layer = 4 - ((header[1] >> 1) & 0x03);
+        if version == 1 || layer == 4 {
+            return None;
+        }
+        let mpeg1 = version == 3;
+        let bitrates = match (mpeg1, layer) {
+            (true, 1) => &MPEG1_LAYER1_BITRATES,
+            (true, 2) => &MPEG1_LAYER2_BITRATES,
+            (true, _) => &MPEG1_LAYER3_BITRATES,
+            (false, 1) => &MPEG2_LAYER1_BITRATES,
+            (false, _) => &MPEG2_LAYER2_BITRATES,
+        };
+        let bitrate = *bitrates
+            .get((header[2] >> 4) as usize)
+            .filter(|b| **b > 0)?;
+        let sample_rate = MPEG1_SAMPLE_RATES.get(((header[2] >> 2) & 0x03) as usize)?
+            >> match version {
+                3 => 0,
+                2 => 1,
+                _ => 2,
+            };
+        let padding = u32::from((header[2] >> 1) & 0x01);
+        let (len, samples) = match layer {
+            1 => ((12 * bitrate * 1000 / sample_rate + padding) * 4, 384),
+            2 => (144 * bitrate * 1000 / sample_rate + padding, 1152),
+            _ if mpeg1 => (144 * bitrate * 1000 / sample_rate + padding, 1152),
+            _ => (72 * bitrate * 1000 / sample_rate + padding, 576),
+        };
+        Some(Self {
+            kind: FrameKind::Mpeg,
+            raw: header,
+            len: len as usize,
+            sample_rate,
+            samples,
+            bitrate: Some(bitrate),
+        })
+    }
+
+    fn parse_adts(buf: &[u8]) -> Option<Self> {
+        let header = buf.get(..ADTS_HEADER_LEN)?;
+        // 12 bit sync word followed by a layer that's always 0
+        if header[0] != 0xFF || header[1] & 0xF6 != 0xF0 {
+            return None;
+        }
+        let sample_rate = *ADTS_SAMPLE_RATES.get(((header[2] >> 2) & 0x0F) as usize)?;
+        let len = ((header[3] as usize & 0x03) << 11)
+            | ((header[4] as usize) << 3)
+            | (header[5] as usize >> 5);
+        if len < ADTS_HEADER_LEN {
+            return None;
+        }
+        let blocks = u32::from(header[6] & 0x03) + 1;
+        Some(Self {
+            kind: FrameKind::Adts,
+            raw: header[..4].try_into().ok()?,
+            len,
+            sample_rate,
+            samples: blocks * ADTS_SAMPLES_PER_FRAME,
+            bitrate: None,
+        })
+    }
+
+    /// Container format of the frame.
+    pub fn kind(&self) -> FrameKind {
+        self.kind
+    }
+
+    /// MPEG audio layer, ex: `3` for MP3. Returns `None` for ADTS frames.
+    pub fn layer(&self) -> Option<u8> {
+        match self.kind {
+            FrameKind::Mpeg => Some(4 - ((self.raw[1] >> 1) & 0x03)),
+            FrameKind::Adts => None,
+        }
+    }
+
+    /// Length of the frame in bytes, including the header.
+    pub fn frame_len(&self) -> usize {
+        self.len
+    }
+
+    /// Sample rate of the frame.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of samples per channel in the frame.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Bitrate in kbps. ADTS headers don't include the bitrate.
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bitrate
+    }
+
+    /// Amount of audio in the frame.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(u64::from(self.samples)) / self.sample_rate
+    }
+
+    /// Whether `next` is likely to be the next frame in the same stream as `self`.
+    fn is_compatible(&self, next: &Self) -> bool {
+        // Version, layer, and sample rate are constant within a stream. The bitrate can change
+        // between frames in VBR streams.
+        self.kind == next.kind
+            && self.raw[1] & 0xFE == next.raw[1] & 0xFE
+            && self.sample_rate == next.sample_rate
+    }
+
+    fn is_mpeg1(&self) -> bool {
+        (self.raw[1] >> 3) & 0x03 == 3
+    }
+
+    fn is_mono(&self) -> bool {
+        self.raw[3] >> 6 == 3
+    }
+}
+
+/// Builds a Xing header frame for an MP3 file. `vbr` selects between the `Xing` tag used for VBR
+/// streams and the `Info` tag used for CBR streams. Returns `None` if `first` isn't an MP3 frame
+/// or is too small to hold the tag.
+pub(crate) fn xing_frame(
+    first: &FrameHeader,
+    frames: u32,
+    bytes: u32,
+    vbr: bool,
+) -> Option<Vec<u8>> {
+    if first.layer() != Some(3) {
+        return None;
+    }
+    let mut header = first.raw;
+    // Disable the CRC and padding so the frame length only depends on the bitrate
+    header[1] |= 0x01;
+    let padding = (header[2] >> 1) & 0x01;
+    header[2] &= !0x02;
+    let len = first.len - padding as usize;
+    let side_info_len = match (first.is_mpeg1(), first.is_mono()) {
+        (true, true) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+        (false, false) => 17,
+    };
+    let tag_start = MPEG_HEADER_LEN + side_info_len;
+    // tag (4) + flags (4) + frame count (4) + byte count (4)
+    if len < tag_start + 16 {
+        return None;
+    }
+    let mut frame = vec![0; len];
+    frame[..MPEG_HEADER_LEN].copy_from_slice(&header);
+    let tag: &[u8; 4] = if vbr { b"Xing" } else { b"Info" };
+    let mut fields = tag.to_vec();
+    // Frame and byte count fields are present
+    fields.extend_from_slice(&3u32.to_be_bytes());
+    fields.extend_from_slice(&frames.to_be_bytes());
+    fields.extend_from_slice(&bytes.to_be_bytes());
+    frame[tag_start..tag_start + fields.len()].copy_from_slice(&fields);
+    Some(frame)
+}
+
+/// Splits a byte stream into frames. Anything that isn't part of a frame, such as an ID3 tag or
+/// corrupted data, is passed through without a header.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FrameAligner {
+    pending: Vec<u8>,
+    previous: Option<FrameHeader>,
+}
+
+impl FrameAligner {
+    pub(crate) fn push(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+    }
+
+    /// Number of bytes that have been pushed but not returned yet.
+    pub(crate) fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Header of the frame at the start of the pending data, if any.
+    pub(crate) fn pending_header(&self) -> Option<FrameHeader> {
+        FrameHeader::parse(&self.pending)
+    }
+
+    /// Returns the next frame or chunk of unaligned data. Unaligned chunks are limited to
+    /// `max_unaligned` bytes. If `eof` is set, all pending data is returned even if it doesn't
+    /// contain a complete frame.
+    pub(crate) fn next(
+        &mut self,
+        eof: bool,
+        max_unaligned: usize,
+    ) -> Option<(Option<FrameHeader>, Vec<u8>)> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        if let Some(header) = FrameHeader::parse(&self.pending) {
+            let len = header.len;
+            if len > self.pending.len() && !eof {
+                return None;
+            }
+            // Sync words can appear within the audio data, so a frame is only trusted if it
+            // follows another frame or is followed by one
+            let confirmed = self
+                .previous
+                .as_ref()
+                .is_some_and(|previous| previous.is_compatible(&header))
+                || match self.pending.get(len..).and_then(FrameHeader::parse) {
+                    Some(next) => header.is_compatible(&next),
+                    None if self.pending.len() < len + MAX_HEADER_LEN && !eof => return None,
+                    None => false,
+                };
+            if confirmed && len <= self.pending.len() {
+                self.previous = Some(header.clone());
+                return Some((Some(header), self.pending.drain(..len).collect()));
+            }
+        } else if self.pending[0] == 0xFF && self.pending.len() < MAX_HEADER_LEN && !eof {
+            // Might be the start of a header
+            return None;
+        }
+        self.previous = None;
+        // Skip to the next possible sync word
+        let end = self.pending[1..]
+            .iter()
+            .position(|b| *b == 0xFF)
+            .map_or(self.pending.len(), |i| i + 1)
+            .min(max_unaligned.max(1));
+        Some((None, self.pending.drain(..end).collect()))
+    }
+}
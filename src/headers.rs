@@ -165,6 +165,145 @@ impl IcyHeaders {
         }
     }
 
+    /// Sets the audio info from a string in the same format as the `ice-audio-info` header.
+    pub(crate) fn with_audio_info(mut self, audio_info: &str) -> Self {
+        let ParseResult { map, .. } = parse_delimited_string(audio_info);
+        self.audio_info = Some(IcyAudioInfo::parse_from_map(map));
+        self
+    }
+
+    /// Sets the stream bitrate.
+    pub fn with_bitrate(mut self, bitrate: u32) -> Self {
+        self.bitrate = Some(bitrate);
+        self
+    }
+
+    /// Sets the stream sample rate.
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Sets the number of channels in the stream.
+    pub fn with_channels(mut self, channels: u16) -> Self {
+        self.audio_info.get_or_insert_default().channels = Some(channels);
+        self
+    }
+
+    /// Sets the stream quality.
+    pub fn with_quality<S>(mut self, quality: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.audio_info.get_or_insert_default().quality = Some(quality.into());
+        self
+    }
+
+    /// Sets the stream genres.
+    pub fn with_genre<I, S>(mut self, genre: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.genre = genre.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the stream name.
+    pub fn with_name<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the stream description.
+    pub fn with_description<S>(mut self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the stream station URL.
+    pub fn with_station_url<S>(mut self, station_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.station_url = Some(station_url.into());
+        self
+    }
+
+    /// Sets whether the stream should be listed or not.
+    pub fn with_public(mut self, public: bool) -> Self {
+        self.public = Some(public);
+        self
+    }
+
+    /// Converts the values into the `ice-*` headers that Icecast expects from a source client.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the header map size is exceeded.
+    pub fn to_header_map(&self) -> HeaderMap {
+        let mut header_map = HeaderMap::new();
+        let mut append = |name: &'static str, value: &str| {
+            // Values that can't be represented in a header are skipped rather than sent mangled
+            if let Ok(value) = value.parse() {
+                header_map.append(name, value);
+            }
+        };
+        if let Some(name) = &self.name {
+            append("ice-name", name);
+        }
+        if let Some(description) = &self.description {
+            append("ice-description", description);
+        }
+        if let Some(station_url) = &self.station_url {
+            append("ice-url", station_url);
+        }
+        if !self.genre.is_empty() {
+            append("ice-genre", &self.genre.join(","));
+        }
+        if let Some(public) = self.public {
+            append("ice-public", if public { "1" } else { "0" });
+        }
+        if let Some(bitrate) = self.bitrate() {
+            append("ice-bitrate", &bitrate.to_string());
+        }
+
+        let mut audio_info = Vec::new();
+        if let Some(sample_rate) = self.sample_rate() {
+            audio_info.push(format!("ice-samplerate={sample_rate}"));
+        }
+        if let Some(bitrate) = self.bitrate() {
+            audio_info.push(format!("ice-bitrate={bitrate}"));
+        }
+        if let Some(channels) = self.channels() {
+            audio_info.push(format!("ice-channels={channels}"));
+        }
+        if let Some(quality) = self.quality() {
+            audio_info.push(format!("ice-quality={}", urlencoding::encode(&quality)));
+        }
+        let mut custom: Vec<_> = self.custom().into_iter().collect();
+        // Sort for a stable header value
+        custom.sort();
+        for (key, value) in custom {
+            audio_info.push(format!(
+                "{}={}",
+                urlencoding::encode(&key),
+                urlencoding::encode(&value)
+            ));
+        }
+        if !audio_info.is_empty() {
+            append("ice-audio-info", &audio_info.join(";"));
+        }
+
+        header_map
+    }
+
     fn audio_info_prop<F, T>(&self, f: F) -> Option<T>
     where
         F: Fn(&IcyAudioInfo) -> Option<T>,
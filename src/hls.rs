@@ -0,0 +1,619 @@
+//! Metadata for HTTP Live Streaming (HLS) streams.
+//!
+//! HLS streams send metadata in two places: `#EXTINF` and `#EXT-X-DATERANGE` tags in the media
+//! playlist, and timed ID3 tags embedded in the segments. [`MediaPlaylist`] parses the playlist
+//! and [`segment_metadata`] extracts the ID3 tags from a segment along with their presentation
+//! timestamps.
+//!
+//! Nothing in this module performs any requests, so it can be used with any HTTP client.
+
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::error::{InvalidPlaylistError, MetadataParseError};
+use crate::frame::{FrameHeader, FrameKind};
+use crate::{IcyMetadata, id3};
+
+/// Owner of the `PRIV` frame that packed audio segments use to store their starting timestamp.
+const TRANSPORT_STREAM_TIMESTAMP: &str = "com.apple.streaming.transportStreamTimestamp";
+const TS_PACKET_LEN: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+const PAT_PID: u16 = 0;
+const PAT_TABLE_ID: u8 = 0x00;
+const PMT_TABLE_ID: u8 = 0x02;
+// Stream type for ID3 metadata carried in PES packets
+const METADATA_STREAM_TYPE: u8 = 0x15;
+const PTS_CLOCK_RATE: u64 = 90_000;
+// Timestamps are 33 bits
+const PTS_MASK: u64 = (1 << 33) - 1;
+
+/// HLS media playlist.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MediaPlaylist {
+    target_duration: Option<Duration>,
+    media_sequence: u64,
+    segments: Vec<Segment>,
+    date_ranges: Vec<DateRange>,
+    end_list: bool,
+}
+
+impl MediaPlaylist {
+    /// Parses a media playlist from the response body.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, InvalidPlaylistError> {
+        String::from_utf8_lossy(data).parse()
+    }
+
+    /// Maximum segment duration, from the `#EXT-X-TARGETDURATION` tag.
+    pub fn target_duration(&self) -> Option<Duration> {
+        self.target_duration
+    }
+
+    /// Sequence number of the first segment, from the `#EXT-X-MEDIA-SEQUENCE` tag.
+    pub fn media_sequence(&self) -> u64 {
+        self.media_sequence
+    }
+
+    /// Segments in the playlist.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Date ranges from `#EXT-X-DATERANGE` tags.
+    pub fn date_ranges(&self) -> &[DateRange] {
+        &self.date_ranges
+    }
+
+    /// Whether the playlist contains the `#EXT-X-ENDLIST` tag, meaning no more segments will be
+    /// added.
+    pub fn is_ended(&self) -> bool {
+        self.end_list
+    }
+}
+
+impl FromStr for MediaPlaylist {
+    type Err = InvalidPlaylistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: String| InvalidPlaylistError(message);
+
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next().map(|line| line.trim_start_matches('\u{feff}')) != Some("#EXTM3U") {
+            return Err(invalid("missing #EXTM3U header".to_string()));
+        }
+
+        let mut playlist = Self::default();
+        let mut duration = None;
+        let mut title = None;
+        let mut program_date_time = None;
+        for line in lines {
+            let Some(tag) = line.strip_prefix('#') else {
+                playlist.segments.push(Segment {
+                    uri: line.to_string(),
+                    duration: duration.take().unwrap_or_default(),
+                    title: title.take(),
+                    sequence: playlist.media_sequence + playlist.segments.len() as u64,
+                    program_date_time: program_date_time.take(),
+                });
+                continue;
+            };
+            let (name, value) = tag.split_once(':').unwrap_or((tag, ""));
+            match name {
+                "EXTINF" => {
+                    let (length, segment_title) = value.split_once(',').unwrap_or((value, ""));
+                    duration =
+                        Some(parse_duration(length).ok_or_else(|| {
+                            invalid(format!("invalid segment duration {length}"))
+                        })?);
+                    let segment_title = segment_title.trim();
+                    title = (!segment_title.is_empty()).then(|| segment_title.to_string());
+                }
+                "EXT-X-TARGETDURATION" => {
+                    playlist.target_duration = parse_duration(value);
+                }
+                "EXT-X-MEDIA-SEQUENCE" => {
+                    playlist.media_sequence = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid media sequence {value}")))?;
+                }
+                "EXT-X-PROGRAM-DATE-TIME" => program_date_time = Some(value.trim().to_string()),
+                "EXT-X-DATERANGE" => playlist.date_ranges.push(value.parse()?),
+                "EXT-X-ENDLIST" => playlist.end_list = true,
+                "EXT-X-STREAM-INF" | "EXT-X-I-FRAME-STREAM-INF" => {
+                    return Err(invalid(
+                        "expected a media playlist, but found a master playlist".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(playlist)
+    }
+}
+
+/// Media segment in an HLS playlist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    uri: String,
+    duration: Duration,
+    title: Option<String>,
+    sequence: u64,
+    program_date_time: Option<String>,
+}
+
+impl Segment {
+    /// Segment URI. This may be relative to the playlist URL.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Segment duration from the `#EXTINF` tag.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Title from the `#EXTINF` tag.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Media sequence number of the segment.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Value of the `#EXT-X-PROGRAM-DATE-TIME` tag applied to this segment, as an ISO 8601 date.
+    pub fn program_date_time(&self) -> Option<&str> {
+        self.program_date_time.as_deref()
+    }
+
+    /// Metadata contained in the segment title.
+    ///
+    /// Some broadcasters send attributes in the title, ex: `title="Title",artist="Artist"`. The
+    /// `title`, `artist`, and `url` attributes are mapped to [`IcyMetadata::title`],
+    /// [`IcyMetadata::artist`], and [`IcyMetadata::stream_url`]. Other attributes are stored in
+    /// [`IcyMetadata::custom_fields`]. The title is only treated as a list of attributes if it
+    /// contains one of these known attributes or a quoted value, so titles such as `E=MC2` aren't
+    /// split. Otherwise, it's used as the stream title.
+    pub fn metadata(&self) -> Option<IcyMetadata> {
+        let title = self.title.as_deref()?;
+        let mut metadata = IcyMetadata::default();
+        let attributes = parse_attributes(title).and_then(|(attributes, quoted)| {
+            let known = attributes.iter().any(|(key, _)| {
+                ["title", "artist", "url"]
+                    .iter()
+                    .any(|name| key.eq_ignore_ascii_case(name))
+            });
+            (known || quoted).then_some(attributes)
+        });
+        let Some(attributes) = attributes else {
+            metadata.stream_title = Some(title.to_string());
+            return Some(metadata);
+        };
+        for (key, value) in attributes {
+            match key.to_ascii_lowercase().as_str() {
+                "title" => metadata.title = Some(value),
+                "artist" => metadata.artist = Some(value),
+                "url" => metadata.stream_url = Some(value.into()),
+                _ => {
+                    metadata.custom.insert(key, value);
+                }
+            }
+        }
+        metadata.fill_stream_title();
+        Some(metadata)
+    }
+}
+
+/// Range of time from an `#EXT-X-DATERANGE` tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DateRange {
+    id: String,
+    class: Option<String>,
+    start_date: String,
+    end_date: Option<String>,
+    duration: Option<Duration>,
+    planned_duration: Option<Duration>,
+    attributes: Vec<(String, String)>,
+}
+
+impl DateRange {
+    /// Unique identifier for the date range.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Client-defined class that specifies the meaning of the custom attributes.
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    /// When the date range starts, as an ISO 8601 date.
+    pub fn start_date(&self) -> &str {
+        &self.start_date
+    }
+
+    /// When the date range ends, as an ISO 8601 date.
+    pub fn end_date(&self) -> Option<&str> {
+        self.end_date.as_deref()
+    }
+
+    /// Duration of the date range.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Expected duration of the date range if the actual duration isn't known yet.
+    pub fn planned_duration(&self) -> Option<Duration> {
+        self.planned_duration
+    }
+
+    /// All attributes in the tag, including the ones available through other accessors.
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    /// Returns the value of the attribute with the given name.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Metadata contained in the client-defined `X-` attributes.
+    ///
+    /// `X-TITLE`, `X-ARTIST`, and `X-ALBUM` are mapped to [`IcyMetadata::title`],
+    /// [`IcyMetadata::artist`], and [`IcyMetadata::album`]. Other client-defined attributes are
+    /// stored in [`IcyMetadata::custom_fields`].
+    pub fn metadata(&self) -> Option<IcyMetadata> {
+        let mut metadata = IcyMetadata::default();
+        for (key, value) in &self.attributes {
+            let upper_key = key.to_ascii_uppercase();
+            if !upper_key.starts_with("X-") {
+                continue;
+            }
+            match upper_key.as_str() {
+                "X-TITLE" => metadata.title = Some(value.clone()),
+                "X-ARTIST" => metadata.artist = Some(value.clone()),
+                "X-ALBUM" => metadata.album = Some(value.clone()),
+                _ => {
+                    metadata.custom.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        metadata.fill_stream_title();
+        (metadata != IcyMetadata::default()).then_some(metadata)
+    }
+}
+
+impl FromStr for DateRange {
+    type Err = InvalidPlaylistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (attributes, _) = parse_attributes(s)
+            .ok_or_else(|| InvalidPlaylistError(format!("invalid date range attributes {s}")))?;
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        let required = |name: &str| {
+            attribute(name)
+                .ok_or_else(|| InvalidPlaylistError(format!("date range is missing {name}")))
+        };
+        Ok(Self {
+            id: required("ID")?,
+            class: attribute("CLASS"),
+            start_date: required("START-DATE")?,
+            end_date: attribute("END-DATE"),
+            duration: attribute("DURATION").and_then(|d| parse_duration(&d)),
+            planned_duration: attribute("PLANNED-DURATION").and_then(|d| parse_duration(&d)),
+            attributes,
+        })
+    }
+}
+
+/// Metadata from a timed ID3 tag, along with the presentation timestamp it applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedMetadata {
+    pts: Option<u64>,
+    metadata: IcyMetadata,
+}
+
+impl TimedMetadata {
+    /// Presentation timestamp in units of the 90 kHz MPEG clock. This is `None` if the segment
+    /// doesn't contain any timing information.
+    pub fn pts(&self) -> Option<u64> {
+        self.pts
+    }
+
+    /// Presentation timestamp converted to a [`Duration`].
+    pub fn presentation_time(&self) -> Option<Duration> {
+        self.pts
+            .map(|pts| Duration::from_nanos(pts * 1_000_000_000 / PTS_CLOCK_RATE))
+    }
+
+    /// Metadata parsed from the tag.
+    pub fn metadata(&self) -> &IcyMetadata {
+        &self.metadata
+    }
+
+    /// Converts into the metadata parsed from the tag.
+    pub fn into_metadata(self) -> IcyMetadata {
+        self.metadata
+    }
+}
+
+/// Extracts timed ID3 metadata from a segment. Both MPEG-TS segments and packed audio segments,
+/// such as raw AAC, are supported.
+///
+/// MPEG-TS segments carry tags in a separate elementary stream and take their timestamps from the
+/// PES packets. Packed audio segments use a `PRIV` frame to specify the timestamp of the first
+/// audio frame. Tags that only contain the timestamp aren't returned.
+pub fn segment_metadata(segment: &[u8]) -> Vec<Result<TimedMetadata, MetadataParseError>> {
+    let is_transport_stream = segment.len() >= TS_PACKET_LEN
+        && segment[0] == TS_SYNC_BYTE
+        && segment
+            .get(TS_PACKET_LEN)
+            .is_none_or(|sync| *sync == TS_SYNC_BYTE);
+    if is_transport_stream {
+        transport_stream_metadata(segment)
+    } else {
+        packed_audio_metadata(segment)
+    }
+}
+
+fn packed_audio_metadata(segment: &[u8]) -> Vec<Result<TimedMetadata, MetadataParseError>> {
+    let mut events = Vec::new();
+    let mut pts = None;
+    let mut pos = 0;
+    while pos < segment.len() {
+        let rest = &segment[pos..];
+        if let Some(tag_len) = id3::tag_len(rest).filter(|len| *len <= rest.len()) {
+            let tag = &rest[..tag_len];
+            if let Some(timestamp) = id3::parse_frames(tag)
+                .ok()
+                .and_then(|frames| transport_stream_timestamp(&frames))
+            {
+                pts = Some(timestamp);
+            }
+            events.extend(timed_metadata(tag, pts));
+            pos += tag_len;
+        } else if let Some((frame_len, frame_ticks)) = adts_frame(rest) {
+            // Later tags apply to the audio frame that follows them
+            pts = pts.map(|pts| (pts + frame_ticks) & PTS_MASK);
+            pos += frame_len;
+        } else {
+            pos += 1;
+        }
+    }
+    events
+}
+
+fn transport_stream_metadata(segment: &[u8]) -> Vec<Result<TimedMetadata, MetadataParseError>> {
+    let mut events = Vec::new();
+    let mut pmt_pids = HashSet::new();
+    let mut metadata_pids = HashSet::new();
+    let mut pes_packets = BTreeMap::new();
+    for packet in segment.chunks_exact(TS_PACKET_LEN) {
+        if packet[0] != TS_SYNC_BYTE {
+            warn!("lost sync with transport stream");
+            continue;
+        }
+        let payload_start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+        let adaptation_field_control = (packet[3] >> 4) & 0x03;
+        if adaptation_field_control & 0x01 == 0 {
+            continue;
+        }
+        let payload_offset = if adaptation_field_control & 0x02 != 0 {
+            5 + packet[4] as usize
+        } else {
+            4
+        };
+        let Some(payload) = packet.get(payload_offset..) else {
+            continue;
+        };
+
+        if pid == PAT_PID {
+            if payload_start {
+                pmt_pids.extend(parse_pat(payload));
+            }
+        } else if pmt_pids.contains(&pid) {
+            if payload_start {
+                metadata_pids.extend(parse_pmt(payload));
+            }
+        } else if metadata_pids.contains(&pid) {
+            if payload_start {
+                if let Some(pes) = pes_packets.insert(pid, payload.to_vec()) {
+                    events.extend(pes_metadata(&pes));
+                }
+            } else if let Some(pes) = pes_packets.get_mut(&pid) {
+                pes.extend_from_slice(payload);
+            }
+        }
+    }
+    for pes in pes_packets.values() {
+        events.extend(pes_metadata(pes));
+    }
+    events
+}
+
+/// Returns the table ID and the section data between the header and the CRC.
+fn psi_section(payload: &[u8]) -> Option<(u8, &[u8])> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+    let table_id = *section.first()?;
+    let section_len = u16::from_be_bytes([section.get(1)? & 0x0F, *section.get(2)?]) as usize;
+    let data = section.get(3..3 + section_len.checked_sub(4)?)?;
+    Some((table_id, data))
+}
+
+fn parse_pat(payload: &[u8]) -> Vec<u16> {
+    let Some((PAT_TABLE_ID, data)) = psi_section(payload) else {
+        return Vec::new();
+    };
+    // transport stream ID (2) + version (1) + section number (1) + last section number (1)
+    data.get(5..)
+        .unwrap_or_default()
+        .chunks_exact(4)
+        // program 0 points to the network information table
+        .filter(|program| program[..2] != [0, 0])
+        .map(|program| u16::from_be_bytes([program[2] & 0x1F, program[3]]))
+        .collect()
+}
+
+fn parse_pmt(payload: &[u8]) -> Vec<u16> {
+    let mut pids = Vec::new();
+    let Some((PMT_TABLE_ID, data)) = psi_section(payload) else {
+        return pids;
+    };
+    // program number (2) + version (1) + section number (1) + last section number (1) +
+    // PCR PID (2) + program info length (2)
+    let Some(program_info_len) = data
+        .get(7..9)
+        .map(|len| u16::from_be_bytes([len[0] & 0x0F, len[1]]) as usize)
+    else {
+        return pids;
+    };
+    let mut pos = 9 + program_info_len;
+    while let Some(stream) = data.get(pos..pos + 5) {
+        let pid = u16::from_be_bytes([stream[1] & 0x1F, stream[2]]);
+        if stream[0] == METADATA_STREAM_TYPE {
+            pids.push(pid);
+        }
+        pos += 5 + u16::from_be_bytes([stream[3] & 0x0F, stream[4]]) as usize;
+    }
+    pids
+}
+
+fn pes_metadata(pes: &[u8]) -> Vec<Result<TimedMetadata, MetadataParseError>> {
+    let mut events = Vec::new();
+    if !pes.starts_with(&[0, 0, 1]) || pes.len() < 9 {
+        return events;
+    }
+    let pts_present = pes[7] & 0x80 != 0;
+    let payload_start = 9 + pes[8] as usize;
+    let pts = pes.get(9..14).filter(|_| pts_present).map(|pts| {
+        (u64::from((pts[0] >> 1) & 0x07) << 30)
+            | (u64::from(pts[1]) << 22)
+            | (u64::from(pts[2] >> 1) << 15)
+            | (u64::from(pts[3]) << 7)
+            | u64::from(pts[4] >> 1)
+    });
+    // A length of 0 means the packet continues until the next one starts
+    let pes_len = u16::from_be_bytes([pes[4], pes[5]]) as usize;
+    let payload_end = if pes_len == 0 {
+        pes.len()
+    } else {
+        (6 + pes_len).min(pes.len())
+    };
+    let mut payload = pes.get(payload_start..payload_end).unwrap_or_default();
+    while let Some(tag_len) = id3::tag_len(payload).filter(|len| *len <= payload.len()) {
+        events.extend(timed_metadata(&payload[..tag_len], pts));
+        payload = &payload[tag_len..];
+    }
+    events
+}
+
+fn timed_metadata(
+    tag: &[u8],
+    pts: Option<u64>,
+) -> Option<Result<TimedMetadata, MetadataParseError>> {
+    match IcyMetadata::from_id3(tag) {
+        Ok(mut metadata) => {
+            metadata.custom.remove(TRANSPORT_STREAM_TIMESTAMP);
+            (metadata != IcyMetadata::default()).then_some(Ok(TimedMetadata { pts, metadata }))
+        }
+        Err(e) => Some(Err(e)),
+    }
+}
+
+fn transport_stream_timestamp(frames: &[id3::Frame]) -> Option<u64> {
+    frames
+        .iter()
+        .filter(|frame| frame.id == "PRIV")
+        .find_map(|frame| {
+            let timestamp = frame
+                .data
+                .strip_prefix(TRANSPORT_STREAM_TIMESTAMP.as_bytes())?
+                .strip_prefix(&[0])?;
+            Some(u64::from_be_bytes(timestamp.get(..8)?.try_into().ok()?) & PTS_MASK)
+        })
+}
+
+/// Returns the length and duration in PTS ticks of the ADTS frame at the start of `buf`.
+fn adts_frame(buf: &[u8]) -> Option<(usize, u64)> {
+    let header = FrameHeader::parse(buf).filter(|header| header.kind() == FrameKind::Adts)?;
+    Some((
+        header.frame_len(),
+        u64::from(header.samples()) * PTS_CLOCK_RATE / u64::from(header.sample_rate()),
+    ))
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.trim().parse().ok()?).ok()
+}
+
+/// Parses an attribute list, ex: `ID="ad",START-DATE="2024-01-01T00:00:00Z",DURATION=30`. Returns
+/// `None` if `s` isn't a valid attribute list. The second value is whether any of the values were
+/// quoted.
+fn parse_attributes(s: &str) -> Option<(Vec<(String, String)>, bool)> {
+    let mut attributes = Vec::new();
+    let mut any_quoted = false;
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+        let value = value.trim_start();
+        let (value, remaining) = if let Some(quoted) = value.strip_prefix('"') {
+            any_quoted = true;
+            let (value, remaining) = split_quoted(quoted)?;
+            let remaining = remaining.trim_start();
+            if !remaining.is_empty() && !remaining.starts_with(',') {
+                return None;
+            }
+            (value, remaining.strip_prefix(',').unwrap_or(remaining))
+        } else {
+            let (value, remaining) = value.split_once(',').unwrap_or((value, ""));
+            (value.trim().to_string(), remaining)
+        };
+        attributes.push((key.to_string(), value));
+        rest = remaining.trim_start();
+    }
+    (!attributes.is_empty()).then_some((attributes, any_quoted))
+}
+
+/// Splits a quoted string from the rest of the input. Some broadcasters escape quotes within
+/// values even though the spec doesn't allow it, so escaped quotes are handled too.
+fn split_quoted(s: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    if !matches!(escaped, '"' | '\\') {
+                        value.push('\\');
+                    }
+                    value.push(escaped);
+                }
+            }
+            '"' => return Some((value, &s[i + 1..])),
+            c => value.push(c),
+        }
+    }
+    None
+}
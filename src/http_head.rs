@@ -0,0 +1,142 @@
+use std::io::{self, Read};
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use http::header::{HeaderName, HeaderValue};
+use http::{HeaderMap, StatusCode};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::warn;
+
+// Response heads from streaming servers are tiny, anything larger than this is almost certainly
+// not a valid response
+const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// Status line and headers of an HTTP (or ICY) response.
+#[derive(Clone, Debug)]
+pub(crate) struct ResponseHead {
+    pub(crate) protocol: String,
+    pub(crate) status: StatusCode,
+    pub(crate) reason: String,
+    pub(crate) headers: HeaderMap,
+    /// The head exactly as it was received, including the blank line at the end.
+    pub(crate) raw: Vec<u8>,
+}
+
+pub(crate) fn basic_auth(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        BASE64_STANDARD.encode(format!("{username}:{password}"))
+    )
+}
+
+pub(crate) fn write_request_head(
+    method: &str,
+    path: &str,
+    version: &str,
+    headers: &HeaderMap,
+) -> Vec<u8> {
+    let mut head = format!("{method} {path} {version}\r\n").into_bytes();
+    for (name, value) in headers {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    head
+}
+
+fn head_complete(buf: &[u8]) -> bool {
+    // Some older servers terminate lines with a bare LF
+    buf.ends_with(b"\n\n") || buf.ends_with(b"\n\r\n")
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn check_head_len(buf: &[u8]) -> io::Result<()> {
+    if buf.len() > MAX_HEAD_LEN {
+        return Err(invalid_data("response head exceeded the maximum length"));
+    }
+    Ok(())
+}
+
+pub(crate) fn read_response_head<R>(reader: &mut R) -> io::Result<ResponseHead>
+where
+    R: Read,
+{
+    // Read a byte at a time so we don't consume any of the body
+    let mut buf = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head_complete(&buf) {
+        reader.read_exact(&mut byte)?;
+        buf.push(byte[0]);
+        check_head_len(&buf)?;
+    }
+    parse_response_head(&buf)
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn read_response_head_async<R>(reader: &mut R) -> io::Result<ResponseHead>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    while !head_complete(&buf) {
+        buf.push(reader.read_u8().await?);
+        check_head_len(&buf)?;
+    }
+    parse_response_head(&buf)
+}
+
+fn parse_response_head(buf: &[u8]) -> io::Result<ResponseHead> {
+    let head = String::from_utf8_lossy(buf);
+    let mut lines = head.lines().map(|line| line.trim_end_matches('\r'));
+    let status_line = lines
+        .next()
+        .ok_or_else(|| invalid_data("missing status line"))?;
+    let (protocol, status, reason) = parse_status_line(status_line)?;
+
+    let mut headers = HeaderMap::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        // Older servers aren't always careful about what they send, so skip anything we can't
+        // parse rather than failing the whole response
+        let parsed = line.split_once(':').and_then(|(name, value)| {
+            Some((
+                HeaderName::from_bytes(name.trim_ascii().as_bytes()).ok()?,
+                HeaderValue::from_str(value.trim_ascii()).ok()?,
+            ))
+        });
+        match parsed {
+            Some((name, value)) => {
+                headers.append(name, value);
+            }
+            None => warn!(line, "skipping invalid header line"),
+        }
+    }
+
+    Ok(ResponseHead {
+        protocol,
+        status,
+        reason,
+        headers,
+        raw: buf.to_vec(),
+    })
+}
+
+fn parse_status_line(line: &str) -> io::Result<(String, StatusCode, String)> {
+    let mut parts = line.splitn(3, ' ');
+    let protocol = parts.next().unwrap_or_default();
+    // Shoutcast v1 servers reply with "ICY 200 OK" instead of a standard HTTP status line
+    if protocol != "ICY" && !protocol.starts_with("HTTP/") {
+        return Err(invalid_data(format!("invalid status line: {line}")));
+    }
+    let status = parts
+        .next()
+        .and_then(|status| StatusCode::from_bytes(status.as_bytes()).ok())
+        .ok_or_else(|| invalid_data(format!("invalid status code: {line}")))?;
+    let reason = parts.next().unwrap_or_default().trim_ascii().to_string();
+    Ok((protocol.to_string(), status, reason))
+}
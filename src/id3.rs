@@ -0,0 +1,578 @@
+//! ID3 tag extraction for streams that embed tags in the audio data.
+//!
+//! Some stations, along with most HLS and raw AAC sources, send ID3 tags inline with the audio
+//! instead of using icy metadata. [`Id3Reader`] scans the stream for these tags and sends them to a
+//! callback. It can be stacked on top of [`IcyMetadataReader`](crate::IcyMetadataReader) for
+//! streams that use both.
+//!
+//! Tags can also be written with [`IcyMetadata::to_id3`], which is used to tag recorded tracks.
+
+use std::fmt::Debug;
+use std::io::{self, Read};
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use tracing::warn;
+
+use crate::error::{EmptyMetadataError, InvalidId3Error, MetadataParseError};
+use crate::{Artwork, ArtworkKind, IcyHeaders, IcyMetadata};
+
+const TAG_ID: &[u8] = b"ID3";
+// "ID3" + version (2) + flags (1) + size (4)
+const HEADER_LEN: usize = 10;
+// Tags can technically be up to 256 MB, but anything this large is likely a false positive
+const MAX_TAG_LEN: usize = 16 * 1024 * 1024;
+const READ_CHUNK_LEN: usize = 8 * 1024;
+const UTF8_ENCODING: u8 = 3;
+const FRONT_COVER_PICTURE: u8 = 3;
+const PUBLISHER_LOGO_PICTURE: u8 = 20;
+
+/// Reads ID3 tags embedded in an audio stream.
+pub struct Id3Reader<T> {
+    inner: T,
+    pending: Vec<u8>,
+    // Number of bytes at the start of `pending` that have already been scanned
+    passthrough: usize,
+    strip_tags: bool,
+    eof: bool,
+    on_metadata_read: Box<dyn Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync>,
+}
+
+impl<T> Debug for Id3Reader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Id3Reader")
+            .field("inner", &"<inner>")
+            .field("pending", &self.pending.len())
+            .field("passthrough", &self.passthrough)
+            .field("strip_tags", &self.strip_tags)
+            .field("eof", &self.eof)
+            .field("on_metadata_read", &"<on_metadata_read>")
+            .finish()
+    }
+}
+
+impl<T> Id3Reader<T> {
+    /// Creates a new `Id3Reader`. `on_metadata_read` is called whenever a complete tag is found.
+    pub fn new<F>(inner: T, on_metadata_read: F) -> Self
+    where
+        F: Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync + 'static,
+    {
+        Self {
+            inner,
+            pending: Vec::new(),
+            passthrough: 0,
+            strip_tags: false,
+            eof: false,
+            on_metadata_read: Box::new(on_metadata_read),
+        }
+    }
+
+    /// Removes tags from the stream so they aren't passed to the decoder. Defaults to `false`.
+    pub fn strip_tags(mut self, strip_tags: bool) -> Self {
+        self.strip_tags = strip_tags;
+        self
+    }
+
+    /// Handles any complete tags at the start of the pending data and returns the number of bytes
+    /// that can be passed through.
+    fn scan(&mut self) -> usize {
+        loop {
+            let Some(start) = find_tag(&self.pending) else {
+                if self.eof {
+                    return self.pending.len();
+                }
+                // Hold back anything that could be the start of a tag
+                return self.pending.len() - partial_tag_id_len(&self.pending);
+            };
+            if start > 0 {
+                return start;
+            }
+            let tag_len = match tag_len(&self.pending) {
+                Some(tag_len) if tag_len <= self.pending.len() => tag_len,
+                _ if self.eof => {
+                    warn!("stream ended in the middle of an ID3 tag");
+                    return self.pending.len();
+                }
+                _ => return 0,
+            };
+            (self.on_metadata_read)(IcyMetadata::from_id3(&self.pending[..tag_len]));
+            if !self.strip_tags {
+                return tag_len;
+            }
+            self.pending.drain(..tag_len);
+        }
+    }
+}
+
+impl<T> Id3Reader<T>
+where
+    T: Read,
+{
+    fn fill(&mut self) -> io::Result<()> {
+        let len = self.pending.len();
+        self.pending.resize(len + READ_CHUNK_LEN, 0);
+        let result = self.inner.read(&mut self.pending[len..]);
+        self.pending
+            .truncate(len + result.as_ref().copied().unwrap_or_default());
+        if result? == 0 {
+            self.eof = true;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Read for Id3Reader<T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.passthrough == 0 {
+                self.passthrough = self.scan();
+            }
+            if self.passthrough > 0 {
+                let len = self.passthrough.min(buf.len());
+                buf[..len].copy_from_slice(&self.pending[..len]);
+                self.pending.drain(..len);
+                self.passthrough -= len;
+                return Ok(len);
+            }
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+    }
+}
+
+impl IcyMetadata {
+    /// Parses an ID3 tag, including the 10 byte tag header. Versions 2.2 through 2.4 are
+    /// supported.
+    ///
+    /// `TIT2`, `TPE1`, and `TALB` frames map to [`title`](Self::title), [`artist`](Self::artist),
+    /// and [`album`](Self::album). `APIC` frames are returned as [`artwork`](Self::artwork), or as
+    /// [`artwork_urls`](Self::artwork_urls) if the frame contains a link. Other text and URL
+    /// frames are stored in [`custom_fields`](Self::custom_fields) using the frame ID as the key.
+    /// `TXXX` and `WXXX` frames use their description as the key and `PRIV` frames use their owner
+    /// identifier. Binary `PRIV` data is base64-encoded.
+    pub fn from_id3(tag: &[u8]) -> Result<Self, MetadataParseError> {
+        let frames = parse_frames(tag).map_err(MetadataParseError::InvalidId3)?;
+        let mut metadata = Self::default();
+        for frame in frames {
+            let Some((&encoding, data)) = frame.data.split_first() else {
+                continue;
+            };
+            match frame.id.as_str() {
+                "TIT2" => metadata.title = non_empty(decode_text_values(encoding, data)),
+                "TPE1" => metadata.artist = non_empty(decode_text_values(encoding, data)),
+                "TALB" => metadata.album = non_empty(decode_text_values(encoding, data)),
+                "TXXX" | "WXXX" => {
+                    let (description, value) = split_terminated(encoding, data);
+                    let description = decode_text(encoding, description);
+                    let value = if frame.id == "TXXX" {
+                        decode_text_values(encoding, value)
+                    } else {
+                        decode_latin1(value)
+                    };
+                    if !value.is_empty() {
+                        let key = non_empty(description).unwrap_or(frame.id);
+                        metadata.custom.insert(key, value);
+                    }
+                }
+                "APIC" | "PIC" => {
+                    let (mime_type, data) = if frame.id == "APIC" {
+                        let (mime_type, data) = split_terminated(0, data);
+                        let mime_type = non_empty(decode_latin1(mime_type))
+                            .unwrap_or_else(|| "application/octet-stream".to_string());
+                        (mime_type, data)
+                    } else {
+                        let (format, data) = data.split_at(data.len().min(3));
+                        (image_mime_type(&decode_latin1(format)), data)
+                    };
+                    let Some((&picture_type, data)) = data.split_first() else {
+                        continue;
+                    };
+                    let (_description, data) = split_terminated(encoding, data);
+                    if mime_type == "-->" {
+                        metadata.artwork_urls.extend(non_empty(decode_latin1(data)));
+                    } else if !data.is_empty() {
+                        metadata.artwork.push(Artwork::new(
+                            ArtworkKind::from_picture_type(picture_type.into()),
+                            mime_type,
+                            data.to_vec(),
+                        ));
+                    }
+                }
+                "PRIV" => {
+                    let (owner, data) = split_terminated(0, &frame.data);
+                    let value = match std::str::from_utf8(data) {
+                        Ok(value) if !value.contains(|c: char| c.is_control()) => value.to_string(),
+                        _ => BASE64_STANDARD.encode(data),
+                    };
+                    metadata.custom.insert(decode_latin1(owner), value);
+                }
+                id if id.starts_with('T') => {
+                    let value = decode_text_values(encoding, data);
+                    if !value.is_empty() {
+                        metadata.custom.insert(frame.id, value);
+                    }
+                }
+                id if id.starts_with('W') => {
+                    // URL frames don't have an encoding byte
+                    let (url, _) = split_terminated(0, &frame.data);
+                    let url = decode_latin1(url);
+                    if !url.is_empty() {
+                        metadata.custom.insert(frame.id, url);
+                    }
+                }
+                _ => {}
+            }
+        }
+        metadata.fill_stream_title();
+
+        if metadata == Self::default() {
+            return Err(MetadataParseError::Empty(EmptyMetadataError(
+                "ID3 tag".to_string(),
+            )));
+        }
+        Ok(metadata)
+    }
+
+    /// Builds an ID3v2.4 tag from the metadata, including the 10 byte tag header.
+    ///
+    /// The artist and title are written to `TPE1` and `TIT2`. If they weren't sent in a structured
+    /// format, they're taken from a `StreamTitle` in the form `Artist - Title`. The album is
+    /// written to `TALB`, the `StreamUrl` to `WXXX`, and the station name and genre from
+    /// `headers` to `TRSN` and `TCON`. Embedded images are written to `APIC` frames, as are
+    /// artwork URLs using the `-->` link format.
+    pub fn to_id3(&self, headers: Option<&IcyHeaders>) -> Vec<u8> {
+        let mut frames = Vec::new();
+        let (artist, title) = self.artist_and_title();
+        let text_frames = [
+            ("TIT2", title.map(str::to_string)),
+            ("TPE1", artist.map(str::to_string)),
+            ("TALB", self.album.clone()),
+            (
+                "TRSN",
+                headers.and_then(IcyHeaders::name).map(str::to_string),
+            ),
+            (
+                "TCON",
+                // Version 2.4 separates multiple values with a null character
+                headers
+                    .map(|headers| headers.genre().join("\0"))
+                    .filter(|genre| !genre.is_empty()),
+            ),
+        ];
+        for (id, value) in text_frames {
+            if let Some(value) = value {
+                let mut data = vec![UTF8_ENCODING];
+                data.extend_from_slice(value.as_bytes());
+                write_frame(&mut frames, id, &data);
+            }
+        }
+        if let Some(stream_url) = &self.stream_url {
+            // Empty description followed by the URL
+            let mut data = vec![UTF8_ENCODING, 0];
+            data.extend_from_slice(stream_url.as_str().as_bytes());
+            write_frame(&mut frames, "WXXX", &data);
+        }
+        let pictures = self
+            .artwork
+            .iter()
+            .map(|artwork| {
+                let picture_type = match artwork.kind() {
+                    ArtworkKind::Station => PUBLISHER_LOGO_PICTURE,
+                    ArtworkKind::Track => FRONT_COVER_PICTURE,
+                };
+                (artwork.mime_type(), picture_type, artwork.data())
+            })
+            .chain(
+                self.artwork_urls
+                    .iter()
+                    .map(|url| ("-->", FRONT_COVER_PICTURE, url.as_bytes())),
+            );
+        for (mime_type, picture_type, picture) in pictures {
+            let mut data = vec![UTF8_ENCODING];
+            data.extend_from_slice(mime_type.as_bytes());
+            // Mime type terminator, picture type, and empty description
+            data.extend_from_slice(&[0, picture_type, 0]);
+            data.extend_from_slice(picture);
+            write_frame(&mut frames, "APIC", &data);
+        }
+
+        let mut tag = TAG_ID.to_vec();
+        // Version 2.4.0 with no flags
+        tag.extend_from_slice(&[4, 0, 0]);
+        tag.extend_from_slice(&to_syncsafe(frames.len()));
+        tag.extend(frames);
+        tag
+    }
+}
+
+/// A single frame from an ID3 tag. Version 2.2 frame IDs are converted to their version 2.3
+/// equivalents, except for `PIC` since its layout is different from `APIC`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) id: String,
+    pub(crate) data: Vec<u8>,
+}
+
+/// Returns the length of the tag starting at the beginning of `buf`, including the header and
+/// footer. Returns `None` if `buf` doesn't start with a valid tag header.
+pub(crate) fn tag_len(buf: &[u8]) -> Option<usize> {
+    let header = buf.get(..HEADER_LEN)?;
+    let (major, revision, flags) = (header[3], header[4], header[5]);
+    if !header.starts_with(TAG_ID) || !(2..=4).contains(&major) || revision == 0xFF {
+        return None;
+    }
+    let size = syncsafe(&header[6..10])? as usize;
+    let footer_len = if major == 4 && flags & 0x10 != 0 {
+        HEADER_LEN
+    } else {
+        0
+    };
+    let len = HEADER_LEN + size + footer_len;
+    (len <= MAX_TAG_LEN).then_some(len)
+}
+
+pub(crate) fn parse_frames(tag: &[u8]) -> Result<Vec<Frame>, InvalidId3Error> {
+    let invalid = |message: &str| InvalidId3Error(message.to_string());
+
+    let len = tag_len(tag).ok_or_else(|| invalid("invalid tag header"))?;
+    if tag.len() < len {
+        return Err(invalid("tag is truncated"));
+    }
+    let (major, flags) = (tag[3], tag[5]);
+    let size = syncsafe(&tag[6..10]).unwrap_or_default() as usize;
+    let mut body = tag[HEADER_LEN..HEADER_LEN + size].to_vec();
+    let unsynchronized = flags & 0x80 != 0;
+    // Version 2.4 applies unsynchronization to each frame individually
+    if unsynchronized && major < 4 {
+        body = remove_unsynchronization(&body);
+    }
+
+    let mut pos = 0;
+    if flags & 0x40 != 0 && major >= 3 {
+        let extended_len: [u8; 4] = body
+            .get(..4)
+            .and_then(|len| len.try_into().ok())
+            .ok_or_else(|| invalid("extended header is truncated"))?;
+        pos = if major == 3 {
+            // The size doesn't include itself in version 2.3
+            u32::from_be_bytes(extended_len) as usize + 4
+        } else {
+            syncsafe(&extended_len).ok_or_else(|| invalid("invalid extended header size"))? as usize
+        };
+    }
+
+    let (id_len, frame_header_len) = if major == 2 { (3, 6) } else { (4, 10) };
+    let mut frames = Vec::new();
+    while let Some(header) = body.get(pos..pos + frame_header_len) {
+        let id = &header[..id_len];
+        // The rest of the tag is padding
+        if id[0] == 0 {
+            break;
+        }
+        if !id
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            return Err(invalid("invalid frame ID"));
+        }
+        let size = match major {
+            2 => u32::from_be_bytes([0, header[3], header[4], header[5]]),
+            3 => u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+            _ => syncsafe(&header[4..8]).ok_or_else(|| invalid("invalid frame size"))?,
+        } as usize;
+        let start = pos + frame_header_len;
+        let mut data = body
+            .get(start..start + size)
+            .ok_or_else(|| invalid("frame exceeds tag size"))?;
+        pos = start + size;
+
+        let format_flags = if major == 2 { 0 } else { header[9] };
+        let data = match major {
+            3 => {
+                // Skip compressed and encrypted frames
+                if format_flags & 0xC0 != 0 {
+                    continue;
+                }
+                // Grouping identity
+                if format_flags & 0x20 != 0 {
+                    data = data.get(1..).unwrap_or_default();
+                }
+                data.to_vec()
+            }
+            4 => {
+                if format_flags & 0x0C != 0 {
+                    continue;
+                }
+                // Grouping identity
+                if format_flags & 0x40 != 0 {
+                    data = data.get(1..).unwrap_or_default();
+                }
+                // Data length indicator
+                if format_flags & 0x01 != 0 {
+                    data = data.get(4..).unwrap_or_default();
+                }
+                if unsynchronized || format_flags & 0x02 != 0 {
+                    remove_unsynchronization(data)
+                } else {
+                    data.to_vec()
+                }
+            }
+            _ => data.to_vec(),
+        };
+        let id = String::from_utf8_lossy(id);
+        let id = if major == 2 {
+            v22_frame_id(&id).map_or(id, Into::into)
+        } else {
+            id
+        };
+        frames.push(Frame {
+            id: id.into_owned(),
+            data,
+        });
+    }
+    Ok(frames)
+}
+
+/// Finds the start of the first tag that's either valid or too short to check yet.
+fn find_tag(buf: &[u8]) -> Option<usize> {
+    buf.windows(TAG_ID.len())
+        .enumerate()
+        .filter(|(_, window)| *window == TAG_ID)
+        .map(|(pos, _)| pos)
+        .find(|pos| buf.len() - pos < HEADER_LEN || tag_len(&buf[*pos..]).is_some())
+}
+
+fn partial_tag_id_len(buf: &[u8]) -> usize {
+    (1..TAG_ID.len())
+        .rev()
+        .find(|len| buf.ends_with(&TAG_ID[..*len]))
+        .unwrap_or_default()
+}
+
+fn write_frame(frames: &mut Vec<u8>, id: &str, data: &[u8]) {
+    frames.extend_from_slice(id.as_bytes());
+    frames.extend_from_slice(&to_syncsafe(data.len()));
+    // No frame flags
+    frames.extend_from_slice(&[0, 0]);
+    frames.extend_from_slice(data);
+}
+
+fn to_syncsafe(size: usize) -> [u8; 4] {
+    [21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8)
+}
+
+fn syncsafe(bytes: &[u8]) -> Option<u32> {
+    bytes.iter().try_fold(0u32, |size, byte| {
+        (byte & 0x80 == 0).then_some((size << 7) | u32::from(*byte))
+    })
+}
+
+fn remove_unsynchronization(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut prev = 0;
+    for &byte in data {
+        if prev != 0xFF || byte != 0 {
+            output.push(byte);
+        }
+        prev = byte;
+    }
+    output
+}
+
+fn v22_frame_id(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "TT2" => "TIT2",
+        "TP1" => "TPE1",
+        "TAL" => "TALB",
+        "TCO" => "TCON",
+        "TYE" => "TYER",
+        "TRK" => "TRCK",
+        "TXX" => "TXXX",
+        "WXX" => "WXXX",
+        "COM" => "COMM",
+        _ => return None,
+    })
+}
+
+fn image_mime_type(format: &str) -> String {
+    match format.to_ascii_uppercase().as_str() {
+        "JPG" => "image/jpeg".to_string(),
+        "PNG" => "image/png".to_string(),
+        "-->" => format.to_string(),
+        format => format!("image/{}", format.to_ascii_lowercase()),
+    }
+}
+
+/// Splits a string terminated by a null character from the rest of the data.
+fn split_terminated(encoding: u8, data: &[u8]) -> (&[u8], &[u8]) {
+    let end = if matches!(encoding, 1 | 2) {
+        // UTF-16 uses a two byte terminator aligned to the character boundary
+        data.chunks_exact(2)
+            .position(|c| c == [0, 0])
+            .map(|pos| (pos * 2, pos * 2 + 2))
+    } else {
+        data.iter().position(|c| *c == 0).map(|pos| (pos, pos + 1))
+    };
+    match end {
+        Some((end, rest)) => (&data[..end], &data[rest..]),
+        None => (data, &[]),
+    }
+}
+
+fn decode_text(encoding: u8, data: &[u8]) -> String {
+    let text = match encoding {
+        1 => decode_utf16(data, false),
+        2 => decode_utf16(data, true),
+        3 => String::from_utf8_lossy(data).into_owned(),
+        _ => decode_latin1(data),
+    };
+    text.trim_end_matches('\0').trim().to_string()
+}
+
+/// Decodes a text frame, joining multiple values with a comma.
+fn decode_text_values(encoding: u8, data: &[u8]) -> String {
+    decode_text(encoding, data)
+        .split('\0')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn decode_latin1(data: &[u8]) -> String {
+    data.iter()
+        .map(|c| char::from(*c))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn decode_utf16(data: &[u8], big_endian: bool) -> String {
+    let (data, big_endian) = match data {
+        [0xFF, 0xFE, rest @ ..] => (rest, false),
+        [0xFE, 0xFF, rest @ ..] => (rest, true),
+        _ => (data, big_endian),
+    };
+    let units = data.chunks_exact(2).map(|c| {
+        if big_endian {
+            u16::from_be_bytes([c[0], c[1]])
+        } else {
+            u16::from_le_bytes([c[0], c[1]])
+        }
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn non_empty(text: String) -> Option<String> {
+    (!text.is_empty()).then_some(text)
+}
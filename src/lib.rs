@@ -4,10 +4,36 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc = include_str!("../README.md")]
 
+pub mod ad;
+#[cfg(feature = "reqwest")]
+pub mod admin;
+mod artwork;
+pub mod capture;
+pub mod client;
+pub mod cue;
 pub mod error;
+pub mod frame;
 mod headers;
+pub mod hls;
+mod http_head;
+pub mod id3;
+pub mod ogg;
 mod parse;
+pub mod playlist;
 mod reader;
+pub mod recorder;
+#[cfg(feature = "tokio")]
+pub mod source;
+pub mod status;
+pub mod stream_url;
+pub mod timeline;
+pub mod title;
+pub mod triton;
+pub mod ultravox;
+mod xml;
+mod xml_metadata;
+pub mod yp;
 
+pub use artwork::*;
 pub use headers::*;
 pub use reader::*;
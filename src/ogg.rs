@@ -0,0 +1,516 @@
+//! Metadata extraction for Ogg streams.
+//!
+//! Icecast doesn't interleave icy metadata into Ogg streams. Instead, each track change starts a
+//! new logical bitstream with its own comment header. [`OggMetadataReader`] watches for these
+//! headers and sends the parsed comments to a callback.
+//!
+//! Pages with an invalid checksum are skipped and the reader resyncs on the next capture pattern.
+//! When a new logical bitstream starts, the state of the previous one is discarded even if its
+//! last page was never received.
+//!
+//! Vorbis, Opus, and FLAC streams are supported. Embedded images from `METADATA_BLOCK_PICTURE`
+//! comments and FLAC `PICTURE` blocks are returned as [`Artwork`].
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{self, Read};
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use tracing::warn;
+
+use crate::error::{EmptyMetadataError, MetadataParseError};
+use crate::{Artwork, ArtworkKind, IcyMetadata};
+
+const CAPTURE_PATTERN: &[u8] = b"OggS";
+// capture pattern (4) + version (1) + header type (1) + granule position (8) + serial (4) +
+// page sequence (4) + checksum (4) + segment count (1)
+const PAGE_HEADER_LEN: usize = 27;
+// Comment headers can be large if they contain embedded images, but anything past this is likely
+// garbage
+const MAX_HEADER_PACKET_LEN: usize = 16 * 1024 * 1024;
+const FLAC_VORBIS_COMMENT: u8 = 4;
+const FLAC_PICTURE: u8 = 6;
+// Page checksums use CRC-32 with the polynomial 0x04C11DB7, no reflection, and an initial value of
+// 0
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// Flags set in the header of an Ogg page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageFlags(u8);
+
+impl PageFlags {
+    /// The first packet on this page is a continuation of the last packet on the previous page.
+    pub fn is_continued(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    /// This is the first page of a logical bitstream.
+    pub fn is_beginning_of_stream(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// This is the last page of a logical bitstream.
+    pub fn is_end_of_stream(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+}
+
+/// Header of an Ogg page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageHeader {
+    flags: PageFlags,
+    granule_position: u64,
+    serial: u32,
+    sequence: u32,
+    checksum: u32,
+    segment_table: Vec<u8>,
+}
+
+impl PageHeader {
+    /// Parses a page header from the start of `buf`. Returns `None` if `buf` doesn't start with the
+    /// capture pattern or doesn't contain the full header.
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < PAGE_HEADER_LEN || !buf.starts_with(CAPTURE_PATTERN) {
+            return None;
+        }
+        let segment_count = buf[26] as usize;
+        let segment_table = buf.get(PAGE_HEADER_LEN..PAGE_HEADER_LEN + segment_count)?;
+        Some(Self {
+            flags: PageFlags(buf[5]),
+            granule_position: u64::from_le_bytes(buf[6..14].try_into().ok()?),
+            serial: u32::from_le_bytes(buf[14..18].try_into().ok()?),
+            sequence: u32::from_le_bytes(buf[18..22].try_into().ok()?),
+            checksum: u32::from_le_bytes(buf[22..26].try_into().ok()?),
+            segment_table: segment_table.to_vec(),
+        })
+    }
+
+    /// Page flags.
+    pub fn flags(&self) -> PageFlags {
+        self.flags
+    }
+
+    /// Codec-specific position of the last packet that ends on this page.
+    pub fn granule_position(&self) -> u64 {
+        self.granule_position
+    }
+
+    /// Serial number of the logical bitstream this page belongs to.
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    /// Page sequence number within the logical bitstream.
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// CRC-32 checksum of the page.
+    pub fn checksum(&self) -> u32 {
+        self.checksum
+    }
+
+    /// Length of the page header, including the segment table.
+    pub fn header_len(&self) -> usize {
+        PAGE_HEADER_LEN + self.segment_table.len()
+    }
+
+    /// Length of the page body.
+    pub fn body_len(&self) -> usize {
+        self.segment_table.iter().map(|s| *s as usize).sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Codec {
+    Vorbis,
+    Opus,
+    Flac,
+    Unknown,
+}
+
+#[derive(Debug)]
+struct LogicalStream {
+    codec: Option<Codec>,
+    packet_count: usize,
+    packet: Vec<u8>,
+    done: bool,
+    // FLAC spreads its tags over multiple metadata blocks, so they're collected until the last
+    // block is received
+    comments: Option<Vec<(String, String)>>,
+    artwork: Vec<Artwork>,
+}
+
+impl LogicalStream {
+    fn new() -> Self {
+        Self {
+            codec: None,
+            packet_count: 0,
+            packet: Vec::new(),
+            done: false,
+            comments: None,
+            artwork: Vec::new(),
+        }
+    }
+}
+
+/// Reads metadata from the comment headers of an Ogg stream. The stream data is passed through
+/// unmodified.
+pub struct OggMetadataReader<T> {
+    inner: T,
+    pending: Vec<u8>,
+    streams: HashMap<u32, LogicalStream>,
+    // Whether the last page was a BOS page. The BOS pages of all logical bitstreams in a chain
+    // link are grouped together at the start of the link.
+    in_bos_group: bool,
+    on_metadata_read: Box<dyn Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync>,
+}
+
+impl<T> Debug for OggMetadataReader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OggMetadataReader")
+            .field("inner", &"<inner>")
+            .field("pending", &self.pending.len())
+            .field("streams", &self.streams)
+            .field("in_bos_group", &self.in_bos_group)
+            .field("on_metadata_read", &"<on_metadata_read>")
+            .finish()
+    }
+}
+
+impl<T> OggMetadataReader<T> {
+    /// Creates a new `OggMetadataReader`. `on_metadata_read` is called whenever a new comment
+    /// header is found.
+    pub fn new<F>(inner: T, on_metadata_read: F) -> Self
+    where
+        F: Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync + 'static,
+    {
+        Self {
+            inner,
+            pending: Vec::new(),
+            streams: HashMap::new(),
+            in_bos_group: false,
+            on_metadata_read: Box::new(on_metadata_read),
+        }
+    }
+
+    fn process(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        let mut start = 0;
+        loop {
+            let available = &self.pending[start..];
+            if available.len() < CAPTURE_PATTERN.len() {
+                break;
+            }
+            if !available.starts_with(CAPTURE_PATTERN) {
+                // Lost sync, skip ahead to the next page
+                let skip = find_capture_pattern(&available[1..]).map_or(
+                    // Keep the tail in case it's the start of a capture pattern
+                    available.len() - (CAPTURE_PATTERN.len() - 1),
+                    |pos| pos + 1,
+                );
+                warn!(skip, "lost sync with ogg stream");
+                start += skip;
+                continue;
+            }
+            let Some(header) = PageHeader::parse(available) else {
+                break;
+            };
+            let page_len = header.header_len() + header.body_len();
+            if available.len() < page_len {
+                break;
+            }
+            if page_checksum(&available[..page_len]) != header.checksum {
+                // Either the page is corrupt or the capture pattern was part of another page's
+                // data, so look for the next one
+                warn!(serial = header.serial, "ogg page checksum mismatch");
+                start += 1;
+                continue;
+            }
+            let body = available[header.header_len()..page_len].to_vec();
+            self.process_page(&header, &body);
+            start += page_len;
+        }
+        self.pending.drain(..start);
+    }
+
+    fn process_page(&mut self, header: &PageHeader, body: &[u8]) {
+        if header.flags.is_beginning_of_stream() {
+            if !self.in_bos_group {
+                // Start of a new chain link, the previous streams have ended
+                self.streams.clear();
+            }
+            self.in_bos_group = true;
+            self.streams.insert(header.serial, LogicalStream::new());
+        } else {
+            self.in_bos_group = false;
+        }
+        let Some(stream) = self.streams.get_mut(&header.serial) else {
+            return;
+        };
+        if !stream.done {
+            if !header.flags.is_continued() {
+                stream.packet.clear();
+            }
+            let mut offset = 0;
+            let mut packets = Vec::new();
+            for segment in &header.segment_table {
+                let segment = *segment as usize;
+                stream
+                    .packet
+                    .extend_from_slice(&body[offset..offset + segment]);
+                offset += segment;
+                // A segment shorter than 255 bytes terminates the packet
+                if segment < 255 {
+                    packets.push(std::mem::take(&mut stream.packet));
+                }
+            }
+            if stream.packet.len() > MAX_HEADER_PACKET_LEN {
+                warn!(serial = header.serial, "ogg header packet too large");
+                stream.packet.clear();
+                stream.done = true;
+            }
+            for packet in packets {
+                if let Some(metadata) = Self::process_packet(stream, &packet) {
+                    (self.on_metadata_read)(metadata);
+                }
+                if stream.done {
+                    stream.packet.clear();
+                    break;
+                }
+            }
+        }
+        if header.flags.is_end_of_stream() {
+            self.streams.remove(&header.serial);
+        }
+    }
+
+    fn process_packet(
+        stream: &mut LogicalStream,
+        packet: &[u8],
+    ) -> Option<Result<IcyMetadata, MetadataParseError>> {
+        stream.packet_count += 1;
+        let codec = *stream.codec.get_or_insert_with(|| identify_codec(packet));
+        match codec {
+            Codec::Vorbis => {
+                // Vorbis has three header packets: identification, comment, and setup. We only
+                // care about the comment header, which must immediately follow the identification
+                // header
+                if stream.packet_count < 2 {
+                    return None;
+                }
+                stream.done = true;
+                let comments = packet.strip_prefix(b"\x03vorbis")?;
+                Some(parse_comment_metadata(comments))
+            }
+            Codec::Opus => {
+                // The identification header is followed by a single comment header
+                if stream.packet_count < 2 {
+                    return None;
+                }
+                stream.done = true;
+                let comments = packet.strip_prefix(b"OpusTags")?;
+                Some(parse_comment_metadata(comments))
+            }
+            Codec::Flac => {
+                // The first packet contains the mapping header and STREAMINFO block. Each
+                // following header packet contains a single metadata block.
+                if stream.packet_count < 2 {
+                    return None;
+                }
+                let (&block_header, block) = packet.split_first()?;
+                let is_last = block_header & 0x80 != 0;
+                // Skip the 24-bit block length since the packet boundary gives us the same info
+                let block = block.get(3..).unwrap_or_default();
+                match block_header & 0x7F {
+                    FLAC_VORBIS_COMMENT => match parse_vorbis_comments(block) {
+                        Some(comments) => stream.comments = Some(comments),
+                        None => warn!("invalid flac vorbis comment block"),
+                    },
+                    FLAC_PICTURE => match parse_picture(block) {
+                        Some(artwork) => stream.artwork.push(artwork),
+                        None => warn!("invalid flac picture block"),
+                    },
+                    _ => {}
+                }
+                if !is_last {
+                    return None;
+                }
+                stream.done = true;
+                let comments = stream.comments.take();
+                let artwork = std::mem::take(&mut stream.artwork);
+                if comments.is_none() && artwork.is_empty() {
+                    return None;
+                }
+                let mut metadata = metadata_from_comments(comments.unwrap_or_default());
+                metadata.artwork.extend(artwork);
+                Some(Ok(metadata))
+            }
+            Codec::Unknown => {
+                stream.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl<T> Read for OggMetadataReader<T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.process(&buf[..read]);
+        Ok(read)
+    }
+}
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the checksum of a full page. The checksum field itself is treated as zero.
+fn page_checksum(page: &[u8]) -> u32 {
+    page.iter().enumerate().fold(0, |crc, (i, byte)| {
+        let byte = if (22..26).contains(&i) { 0 } else { *byte };
+        (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    })
+}
+
+fn find_capture_pattern(buf: &[u8]) -> Option<usize> {
+    buf.windows(CAPTURE_PATTERN.len())
+        .position(|window| window == CAPTURE_PATTERN)
+}
+
+fn identify_codec(packet: &[u8]) -> Codec {
+    if packet.starts_with(b"\x01vorbis") {
+        Codec::Vorbis
+    } else if packet.starts_with(b"OpusHead") {
+        Codec::Opus
+    } else if packet.starts_with(b"\x7fFLAC") {
+        Codec::Flac
+    } else {
+        Codec::Unknown
+    }
+}
+
+fn parse_comment_metadata(data: &[u8]) -> Result<IcyMetadata, MetadataParseError> {
+    let comments = parse_vorbis_comments(data).ok_or_else(|| {
+        MetadataParseError::Empty(EmptyMetadataError(
+            String::from_utf8_lossy(data).into_owned(),
+        ))
+    })?;
+    Ok(metadata_from_comments(comments))
+}
+
+/// Parses a Vorbis comment block, returning the key/value pairs. Keys are converted to uppercase
+/// since they're case-insensitive.
+pub(crate) fn parse_vorbis_comments(data: &[u8]) -> Option<Vec<(String, String)>> {
+    let mut reader = ByteReader { data, pos: 0 };
+    let vendor_len = reader.u32_le()? as usize;
+    reader.take(vendor_len)?;
+    let count = reader.u32_le()?;
+    let mut comments = Vec::new();
+    for _ in 0..count {
+        let len = reader.u32_le()? as usize;
+        let comment = String::from_utf8_lossy(reader.take(len)?);
+        if let Some((key, value)) = comment.split_once('=') {
+            comments.push((key.to_ascii_uppercase(), value.to_string()));
+        }
+    }
+    Some(comments)
+}
+
+pub(crate) fn metadata_from_comments(comments: Vec<(String, String)>) -> IcyMetadata {
+    let mut metadata = IcyMetadata::default();
+    let mut artists = Vec::new();
+    for (key, value) in comments {
+        match key.as_str() {
+            "TITLE" => metadata.title = Some(value),
+            "ARTIST" => artists.push(value),
+            "ALBUM" => metadata.album = Some(value),
+            "METADATA_BLOCK_PICTURE" => {
+                match BASE64_STANDARD
+                    .decode(value.trim())
+                    .ok()
+                    .and_then(|picture| parse_picture(&picture))
+                {
+                    Some(artwork) => metadata.artwork.push(artwork),
+                    None => warn!("invalid METADATA_BLOCK_PICTURE comment"),
+                }
+            }
+            _ => {
+                // Keys can be repeated, keep all of the values
+                metadata
+                    .custom
+                    .entry(key)
+                    .and_modify(|existing| {
+                        existing.push_str("; ");
+                        existing.push_str(&value);
+                    })
+                    .or_insert(value);
+            }
+        }
+    }
+    if !artists.is_empty() {
+        metadata.artist = Some(artists.join(", "));
+    }
+    metadata.fill_stream_title();
+    metadata
+}
+
+/// Parses a FLAC picture block, which is also used for the `METADATA_BLOCK_PICTURE` comment.
+fn parse_picture(data: &[u8]) -> Option<Artwork> {
+    let mut reader = ByteReader { data, pos: 0 };
+    let picture_type = reader.u32_be()?;
+    let mime_len = reader.u32_be()? as usize;
+    let mime_type = String::from_utf8_lossy(reader.take(mime_len)?).into_owned();
+    let description_len = reader.u32_be()? as usize;
+    // description, width, height, color depth, and number of colors
+    reader.take(description_len.checked_add(16)?)?;
+    let data_len = reader.u32_be()? as usize;
+    let data = reader.take(data_len)?;
+    Some(Artwork::new(
+        ArtworkKind::from_picture_type(picture_type),
+        mime_type,
+        data.to_vec(),
+    ))
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u32_le(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u32_be(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
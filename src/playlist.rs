@@ -0,0 +1,390 @@
+//! Parsing for the playlist formats that station directories link to.
+//!
+//! Directories usually link to a `.pls`, `.m3u`, `.xspf`, or `.asx` file rather than to the stream
+//! itself. [`resolve`] checks whether a response contains one of these playlists or the stream,
+//! and [`Playlist`] extracts the stream URLs from the playlist.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::error::InvalidPlaylistError;
+use crate::xml::Element;
+
+// Enough to get past any leading whitespace or XML declarations
+const DETECT_LEN: usize = 1024;
+
+/// Format of a [`Playlist`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    /// Shoutcast playlist (`.pls`).
+    Pls,
+    /// M3U or M3U8 playlist (`.m3u`, `.m3u8`).
+    M3u,
+    /// XML Shareable Playlist Format (`.xspf`).
+    Xspf,
+    /// Windows Media metafile (`.asx`, `.wax`, `.wvx`). Also covers the INI-style `[Reference]`
+    /// files that some servers return for ASF streams.
+    Asx,
+}
+
+impl PlaylistFormat {
+    /// Determines the playlist format from a `Content-Type` header value. Returns `None` if it's
+    /// not a playlist content type.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match mime_type(content_type).as_str() {
+            "audio/x-scpls" | "audio/scpls" | "application/pls" | "application/pls+xml" => {
+                Some(Self::Pls)
+            }
+            "audio/x-mpegurl"
+            | "audio/mpegurl"
+            | "application/x-mpegurl"
+            | "application/mpegurl"
+            | "application/vnd.apple.mpegurl"
+            | "audio/m3u"
+            | "audio/x-m3u" => Some(Self::M3u),
+            "application/xspf+xml" => Some(Self::Xspf),
+            "video/x-ms-asf" | "video/x-ms-asx" | "audio/x-ms-asx" | "audio/x-ms-wax"
+            | "video/x-ms-wvx" | "video/x-ms-wmx" => Some(Self::Asx),
+            _ => None,
+        }
+    }
+
+    /// Determines the playlist format from the start of the response body. Returns `None` if the
+    /// body doesn't start with a known playlist signature.
+    pub fn detect(body: &[u8]) -> Option<Self> {
+        let start = String::from_utf8_lossy(&body[..body.len().min(DETECT_LEN)]).to_lowercase();
+        let start = start.trim_start_matches('\u{feff}').trim_start();
+        if start.starts_with("[playlist]") {
+            Some(Self::Pls)
+        } else if start.starts_with("#extm3u") {
+            Some(Self::M3u)
+        } else if start.starts_with("[reference]") || start.starts_with("<asx") {
+            Some(Self::Asx)
+        } else if start.starts_with('<') {
+            if start.contains("<asx") {
+                Some(Self::Asx)
+            } else if start.contains("<playlist") {
+                Some(Self::Xspf)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// Stream URL from a playlist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    url: String,
+    title: Option<String>,
+    length: Option<Duration>,
+}
+
+impl PlaylistEntry {
+    /// URL of the stream. This may be relative to the playlist URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Title of the entry, if the playlist specifies one.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Length of the entry. This is `None` for live streams or if the playlist doesn't specify
+    /// one.
+    pub fn length(&self) -> Option<Duration> {
+        self.length
+    }
+}
+
+/// Playlist containing one or more stream URLs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Playlist {
+    format: PlaylistFormat,
+    title: Option<String>,
+    entries: Vec<PlaylistEntry>,
+}
+
+impl Playlist {
+    /// Parses a playlist in the given format. Returns an error if the playlist doesn't contain any
+    /// entries.
+    pub fn parse(format: PlaylistFormat, playlist: &str) -> Result<Self, InvalidPlaylistError> {
+        let playlist = playlist.trim_start_matches('\u{feff}');
+        let (title, entries) = match format {
+            PlaylistFormat::Pls => (None, parse_pls(playlist)),
+            PlaylistFormat::M3u => parse_m3u(playlist),
+            PlaylistFormat::Xspf => parse_xspf(playlist)?,
+            PlaylistFormat::Asx => parse_asx(playlist)?,
+        };
+        if entries.is_empty() {
+            return Err(InvalidPlaylistError(format!(
+                "no entries found in {format:?} playlist"
+            )));
+        }
+        Ok(Self {
+            format,
+            title,
+            entries,
+        })
+    }
+
+    /// Format of the playlist.
+    pub fn format(&self) -> PlaylistFormat {
+        self.format
+    }
+
+    /// Title of the playlist itself, if the format supports it.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Entries in the order they appear in the playlist. Players should try each one in turn
+    /// until one connects.
+    pub fn entries(&self) -> &[PlaylistEntry] {
+        &self.entries
+    }
+}
+
+/// Result of [`resolve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolved {
+    /// The response contained a playlist.
+    Playlist(Playlist),
+    /// The response contained an HLS playlist. These should be handled with the
+    /// [`hls`](crate::hls) module instead.
+    Hls,
+    /// The response contained the stream itself.
+    Stream,
+}
+
+/// Decides whether a response contains a playlist or the stream itself, using the value of the
+/// `Content-Type` header and the response body.
+///
+/// The body is only inspected if it might contain a playlist, so for streams it's fine to pass in
+/// the first chunk of data instead of reading the entire response.
+pub fn resolve(content_type: Option<&str>, body: &[u8]) -> Result<Resolved, InvalidPlaylistError> {
+    let mime_type = content_type.map(mime_type).unwrap_or_default();
+    let format = match PlaylistFormat::detect(body) {
+        Some(format) => format,
+        // Some servers use playlist content types for the streams themselves, ex: video/x-ms-asf
+        None if body.contains(&0) => return Ok(Resolved::Stream),
+        None => match PlaylistFormat::from_content_type(&mime_type) {
+            Some(format) => format,
+            // Bare lists of URLs are sometimes served without a useful content type
+            None if is_generic_text_type(&mime_type) && is_url_list(body) => PlaylistFormat::M3u,
+            None => return Ok(Resolved::Stream),
+        },
+    };
+
+    let body = String::from_utf8_lossy(body);
+    if format == PlaylistFormat::M3u && body.contains("#EXT-X-") {
+        return Ok(Resolved::Hls);
+    }
+    Playlist::parse(format, &body).map(Resolved::Playlist)
+}
+
+fn mime_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn is_generic_text_type(mime_type: &str) -> bool {
+    mime_type.is_empty()
+        || mime_type.starts_with("text/")
+        || mime_type == "application/octet-stream"
+}
+
+fn is_url_list(body: &[u8]) -> bool {
+    let Ok(body) = std::str::from_utf8(body) else {
+        return false;
+    };
+    let mut lines = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .peekable();
+    lines.peek().is_some() && lines.all(|line| line.contains("://"))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn parse_pls(playlist: &str) -> Vec<PlaylistEntry> {
+    #[derive(Default)]
+    struct PlsEntry {
+        url: Option<String>,
+        title: Option<String>,
+        length: Option<Duration>,
+    }
+
+    let mut entries = BTreeMap::<u32, PlsEntry>::new();
+    for line in playlist.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let Some(index_start) = key.find(|c: char| c.is_ascii_digit()) else {
+            continue;
+        };
+        let (field, index) = key.split_at(index_start);
+        let Ok(index) = index.parse() else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.url = non_empty(value),
+            "title" => entry.title = non_empty(value),
+            // Live streams use -1
+            "length" => {
+                entry.length = value.trim().parse::<u64>().ok().map(Duration::from_secs);
+            }
+            _ => {}
+        }
+    }
+    entries
+        .into_values()
+        .filter_map(|entry| {
+            Some(PlaylistEntry {
+                url: entry.url?,
+                title: entry.title,
+                length: entry.length,
+            })
+        })
+        .collect()
+}
+
+fn parse_m3u(playlist: &str) -> (Option<String>, Vec<PlaylistEntry>) {
+    let mut title = None;
+    let mut entries = Vec::new();
+    let mut info = None;
+    for line in playlist
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            let (length, entry_title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            // The length may be followed by attributes, ex: #EXTINF:-1 tvg-id="id",Title
+            let length = length
+                .split_whitespace()
+                .next()
+                .and_then(|length| length.parse::<f64>().ok())
+                .filter(|length| *length > 0.0)
+                .and_then(|length| Duration::try_from_secs_f64(length).ok());
+            info = Some((non_empty(entry_title), length));
+        } else if let Some(playlist_title) = line.strip_prefix("#PLAYLIST:") {
+            title = non_empty(playlist_title);
+        } else if !line.starts_with('#') {
+            let (title, length) = info.take().unwrap_or_default();
+            entries.push(PlaylistEntry {
+                url: line.to_string(),
+                title,
+                length,
+            });
+        }
+    }
+    (title, entries)
+}
+
+fn parse_xml(playlist: &str) -> Result<Element, InvalidPlaylistError> {
+    Element::parse(playlist).map_err(|e| InvalidPlaylistError(e.to_string()))
+}
+
+fn parse_xspf(
+    playlist: &str,
+) -> Result<(Option<String>, Vec<PlaylistEntry>), InvalidPlaylistError> {
+    let root = parse_xml(playlist)?;
+    let mut entries = Vec::new();
+    for track in root
+        .children("trackList")
+        .flat_map(|track_list| track_list.children("track"))
+    {
+        let title = track.child_text("title").map(str::to_string);
+        let length = track
+            .child_text("duration")
+            .and_then(|duration| duration.parse().ok())
+            .map(Duration::from_millis);
+        // Each location is an alternate source for the same track
+        for location in track.children("location") {
+            if let Some(url) = non_empty(&location.text) {
+                entries.push(PlaylistEntry {
+                    url,
+                    title: title.clone(),
+                    length,
+                });
+            }
+        }
+    }
+    Ok((root.child_text("title").map(str::to_string), entries))
+}
+
+fn parse_asx(playlist: &str) -> Result<(Option<String>, Vec<PlaylistEntry>), InvalidPlaylistError> {
+    if playlist
+        .trim_start()
+        .get(..11)
+        .is_some_and(|start| start.eq_ignore_ascii_case("[reference]"))
+    {
+        let entries = playlist
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter(|(key, _)| key.trim().to_ascii_lowercase().starts_with("ref"))
+            .filter_map(|(_, url)| non_empty(url))
+            .map(|url| PlaylistEntry {
+                url,
+                title: None,
+                length: None,
+            })
+            .collect();
+        return Ok((None, entries));
+    }
+
+    let root = parse_xml(playlist)?;
+    let mut entries = Vec::new();
+    for element in &root.children {
+        if element.name.eq_ignore_ascii_case("entryref") {
+            entries.extend(element.attribute("href").and_then(non_empty).map(|url| {
+                PlaylistEntry {
+                    url,
+                    title: None,
+                    length: None,
+                }
+            }));
+        } else if element.name.eq_ignore_ascii_case("entry") {
+            let title = element.child_text("title").map(str::to_string);
+            let length = element
+                .child("duration")
+                .and_then(|duration| duration.attribute("value"))
+                .and_then(parse_clock_time);
+            for reference in element.children("ref") {
+                if let Some(url) = reference.attribute("href").and_then(non_empty) {
+                    entries.push(PlaylistEntry {
+                        url,
+                        title: title.clone(),
+                        length,
+                    });
+                }
+            }
+        }
+    }
+    Ok((root.child_text("title").map(str::to_string), entries))
+}
+
+/// Parses a duration in the form `[[hh:]mm:]ss[.fract]`.
+fn parse_clock_time(value: &str) -> Option<Duration> {
+    let seconds = value.trim().split(':').try_fold(0.0, |total, part| {
+        part.trim()
+            .parse::<f64>()
+            .ok()
+            .map(|part| total * 60.0 + part)
+    })?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
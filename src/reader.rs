@@ -1,13 +1,17 @@
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::num::{NonZero, NonZeroUsize};
 use std::str::FromStr;
 
 use tracing::warn;
 
+use crate::Artwork;
 use crate::error::{EmptyMetadataError, MetadataParseError};
 use crate::parse::{ParseResult, parse_delimited_string, parse_value_if_valid};
+use crate::stream_url::{StreamUrl, StreamUrlKind};
+use crate::title::{DEFAULT_PARSER, ParsedTitle, TitleParser};
+use crate::triton::TritonTitle;
 
 /// Reads icy metadata contained within a stream.
 ///
@@ -23,6 +27,7 @@ use crate::parse::{ParseResult, parse_delimited_string, parse_value_if_valid};
 ///   consecutive metadata entries of the same size don't take up additional slots in the array.
 ///   This means you shouldn't exceed the default value of `128` unless you're going really far
 ///   back.
+/// - Seeking isn't supported when raw data is being copied with [`Self::tee`].
 pub struct IcyMetadataReader<T> {
     inner: T,
     icy_metadata_interval: Option<usize>,
@@ -30,6 +35,7 @@ pub struct IcyMetadataReader<T> {
     metadata_size_queue: MetadataSizeQueue,
     current_pos: u64,
     on_metadata_read: Box<dyn Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync>,
+    tee: Option<Box<dyn Write + Send + Sync>>,
 }
 
 impl<T> Debug for IcyMetadataReader<T> {
@@ -41,6 +47,7 @@ impl<T> Debug for IcyMetadataReader<T> {
             .field("metadata_size_queue", &self.metadata_size_queue)
             .field("current_pos", &self.current_pos)
             .field("on_metadata_read", &"<on_metadata_read>")
+            .field("tee", &self.tee.as_ref().map(|_| "<tee>"))
             .finish()
     }
 }
@@ -72,6 +79,7 @@ impl<T> IcyMetadataReader<T> {
             },
 
             current_pos: 0,
+            tee: None,
         }
     }
 }
@@ -82,6 +90,29 @@ impl<T> IcyMetadataReader<T> {
         self.metadata_size_queue.cache_size = size;
         self
     }
+
+    /// Copies every byte read from the inner stream to `writer`, including the metadata length
+    /// bytes and metadata blocks. This can be used to archive the original stream while reading
+    /// the audio. The raw data is written before the audio is returned, so the two are always in
+    /// sync. Any error from `writer` is returned from the read that caused it.
+    pub fn tee<W>(mut self, writer: W) -> Self
+    where
+        W: Write + Send + Sync + 'static,
+    {
+        self.tee = Some(Box::new(writer));
+        self
+    }
+
+    /// Number of audio bytes that can be read before the next metadata block, or `None` if the
+    /// stream doesn't contain metadata. A value of `0` means the next read will start with a
+    /// metadata block.
+    pub(crate) fn bytes_until_metadata(&self) -> Option<usize> {
+        self.icy_metadata_interval.map(|_| self.next_metadata)
+    }
+
+    pub(crate) fn metadata_interval(&self) -> Option<usize> {
+        self.icy_metadata_interval
+    }
 }
 
 // The metadata length block must be multiplied by 16 to get the total metadata length
@@ -92,6 +123,22 @@ impl<T> IcyMetadataReader<T>
 where
     T: Read,
 {
+    fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(tee) = &mut self.tee {
+            tee.write_all(&buf[..read])?;
+        }
+        Ok(read)
+    }
+
+    fn read_exact_inner(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf)?;
+        if let Some(tee) = &mut self.tee {
+            tee.write_all(buf)?;
+        }
+        Ok(())
+    }
+
     fn parse_metadata_from_stream(&mut self, buf: &mut [u8], metaint: usize) -> io::Result<usize> {
         let to_fill = buf.len();
         let mut total_written = 0;
@@ -117,20 +164,24 @@ where
 
         if self.next_metadata > 0 {
             // Read data before next metadata
-            let written = self.inner.read(&mut buf[..self.next_metadata])?;
-            if written == 0 {
+            let start = *total_written;
+            let end = (start + self.next_metadata).min(to_fill);
+            let written = self.read_inner(&mut buf[start..end])?;
+            *total_written += written;
+            self.next_metadata -= written;
+            // The metadata block isn't due yet if the inner reader returned less than requested or
+            // the buffer is full
+            if self.next_metadata > 0 || *total_written == to_fill {
                 return Ok(());
             }
-            *total_written += written;
         }
 
         self.read_metadata()?;
-        self.next_metadata = metaint;
         let start = *total_written;
 
         // make sure we don't exceed the buffer length
-        let end = (start + self.next_metadata).min(to_fill);
-        let written = self.inner.read(&mut buf[start..end])?;
+        let end = (start + metaint).min(to_fill);
+        let written = self.read_inner(&mut buf[start..end])?;
         *total_written += written;
         self.next_metadata = metaint - written;
         Ok(())
@@ -138,7 +189,7 @@ where
 
     fn update_metadata_size(&mut self) -> io::Result<()> {
         let mut metadata_length_buf = [0u8; 1];
-        self.inner.read_exact(&mut metadata_length_buf)?;
+        self.read_exact_inner(&mut metadata_length_buf)?;
 
         let metadata_length = metadata_length_buf[0] as usize * ICY_METADATA_MULTIPLIER;
 
@@ -151,7 +202,7 @@ where
         if let Some(last_size) = self.metadata_size_queue.peek() {
             if last_size > 0 {
                 let mut metadata_buf = vec![0u8; last_size];
-                self.inner.read_exact(&mut metadata_buf)?;
+                self.read_exact_inner(&mut metadata_buf)?;
 
                 let callback_val = String::from_utf8(metadata_buf)
                     .map_err(MetadataParseError::InvalidUtf8)
@@ -175,13 +226,13 @@ where
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         // Default to normal read behavior if metaint is not set
         let Some(metaint) = self.icy_metadata_interval else {
-            return self.inner.read(buf);
+            return self.read_inner(buf);
         };
 
         if buf.len() > self.next_metadata {
             self.parse_metadata_from_stream(buf, metaint)
         } else {
-            let read = self.inner.read(buf)?;
+            let read = self.read_inner(buf)?;
             self.next_metadata -= read;
             self.current_pos += read as u64;
             Ok(read)
@@ -194,6 +245,12 @@ where
     T: Read + Seek,
 {
     fn seek(&mut self, seek_from: io::SeekFrom) -> io::Result<u64> {
+        if self.tee.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "seek not supported while copying raw data",
+            ));
+        }
         // Default to normal behavior if metaint is not set
         let Some(metaint) = self.icy_metadata_interval else {
             return self.inner.seek(seek_from);
@@ -261,13 +318,105 @@ where
 /// Metadata contained within a stream
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct IcyMetadata {
-    stream_title: Option<String>,
-    stream_url: Option<String>,
-    custom: HashMap<String, String>,
+    pub(crate) stream_title: Option<String>,
+    pub(crate) stream_url: Option<StreamUrl>,
+    pub(crate) title: Option<String>,
+    pub(crate) artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) extension_titles: Vec<String>,
+    pub(crate) artwork_urls: Vec<String>,
+    pub(crate) custom: HashMap<String, String>,
+    pub(crate) artwork: Vec<Artwork>,
+    pub(crate) triton: Option<TritonTitle>,
 }
 
 impl IcyMetadata {
+    /// Sets the title of the currently playing track.
+    pub fn with_stream_title<S>(mut self, stream_title: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.stream_title = Some(stream_title.into());
+        self
+    }
+
+    /// Sets the `StreamUrl` metadata value.
+    pub fn with_stream_url<S>(mut self, stream_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.stream_url = Some(StreamUrl::from(stream_url.into()));
+        self
+    }
+
+    /// Adds an additional field to the metadata.
+    pub fn with_custom_field<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.custom.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds an image to the metadata.
+    pub fn with_artwork(mut self, artwork: Artwork) -> Self {
+        self.artwork.push(artwork);
+        self
+    }
+
+    /// Sets the stream title from the artist and title if it wasn't sent explicitly.
+    pub(crate) fn fill_stream_title(&mut self) {
+        if self.stream_title.is_some() {
+            return;
+        }
+        self.stream_title = match (&self.artist, &self.title) {
+            (Some(artist), Some(title)) => Some(format!("{artist} - {title}")),
+            (None, Some(title)) => Some(title.clone()),
+            (Some(artist), None) => Some(artist.clone()),
+            (None, None) => None,
+        };
+    }
+
+    /// Replaces a Triton-style `StreamTitle` with the structured fields parsed from it. The
+    /// original value is kept if it doesn't contain an artist or title, ex: for an ad spot.
+    fn apply_triton(&mut self) {
+        let Some(triton) = self.stream_title.as_deref().and_then(TritonTitle::parse) else {
+            return;
+        };
+        self.artist = triton.artist().map(str::to_string);
+        self.title = triton.title().map(str::to_string);
+        if let Some(artwork_url) = triton.artwork_url() {
+            self.artwork_urls.push(artwork_url.to_string());
+        }
+        if self.artist.is_some() || self.title.is_some() {
+            self.stream_title = None;
+            self.fill_stream_title();
+        }
+        self.triton = Some(triton);
+    }
+
+    /// Artist and title from the structured fields, falling back to splitting the `StreamTitle`
+    /// with the default [`TitleParser`].
+    pub(crate) fn artist_and_title(&self) -> (Option<&str>, Option<&str>) {
+        let parsed = self.parse_title(&DEFAULT_PARSER);
+        (parsed.artist(), parsed.title())
+    }
+
+    /// Splits the `StreamTitle` into an artist and title using `parser`. If the metadata was sent
+    /// in a structured format, the artist and title are returned as-is instead.
+    pub fn parse_title<'a>(&'a self, parser: &TitleParser) -> ParsedTitle<'a> {
+        if self.artist.is_some() || self.title.is_some() {
+            return ParsedTitle::new(self.artist.as_deref(), self.title.as_deref());
+        }
+        self.stream_title
+            .as_deref()
+            .map(|stream_title| parser.parse(stream_title))
+            .unwrap_or_default()
+    }
+
     /// The title of the currently playing track.
     /// Maps to the `StreamTitle` metadata value.
     pub fn stream_title(&self) -> Option<&str> {
@@ -277,24 +426,85 @@ impl IcyMetadata {
     /// This could be an album art URL, an image URL for the stream itself, or some other
     /// information. Maps to the `StreamUrl` metadata value.
     pub fn stream_url(&self) -> Option<&str> {
-        self.stream_url.as_deref()
+        self.stream_url.as_ref().map(StreamUrl::as_str)
+    }
+
+    /// The track title. Uses the structured value if the metadata was sent in a structured format,
+    /// such as Shoutcast v2 XML metadata. Otherwise, it's parsed from the `StreamTitle` using the
+    /// default [`TitleParser`]. Use [`Self::parse_title`] to customize the parsing.
+    pub fn title(&self) -> Option<&str> {
+        self.artist_and_title().1
+    }
+
+    /// The track artist. Uses the structured value if the metadata was sent in a structured
+    /// format. Otherwise, it's parsed from the `StreamTitle` using the default [`TitleParser`].
+    pub fn artist(&self) -> Option<&str> {
+        self.artist_and_title().0
+    }
+
+    /// The track album. This is only set if the metadata was sent in a structured format.
+    pub fn album(&self) -> Option<&str> {
+        self.album.as_deref()
+    }
+
+    /// Pre-formatted titles sent in the `<extension>` block of Shoutcast v2 XML metadata, ordered
+    /// by their sequence number. The first entry is the current track and any following entries
+    /// are upcoming tracks.
+    pub fn extension_titles(&self) -> &[String] {
+        &self.extension_titles
+    }
+
+    /// URLs of artwork referenced by the metadata.
+    pub fn artwork_urls(&self) -> &[String] {
+        &self.artwork_urls
+    }
+
+    /// Classifies the [`StreamUrl`](Self::stream_url) value. Returns `None` if it's missing or
+    /// empty.
+    pub fn stream_url_kind(&self) -> Option<&StreamUrlKind> {
+        self.stream_url
+            .as_ref()
+            .filter(|stream_url| !stream_url.as_str().trim().is_empty())
+            .map(StreamUrl::kind)
+    }
+
+    /// Best guess at the artwork URL for the current track. Uses the first entry from
+    /// [`Self::artwork_urls`] if there is one. Otherwise, it's taken from the `StreamUrl` if it
+    /// links to an image or contains an artwork field.
+    pub fn artwork_url(&self) -> Option<&str> {
+        if let Some(artwork_url) = self.artwork_urls.first() {
+            return Some(artwork_url);
+        }
+        match self.stream_url_kind()? {
+            StreamUrlKind::Artwork => self.stream_url().map(str::trim),
+            kind => kind.fields()?.artwork_url(),
+        }
     }
 
     /// Any additional fields found in the metadata.
     pub fn custom_fields(&self) -> &HashMap<String, String> {
         &self.custom
     }
+
+    /// Images embedded within the stream. Standard icy metadata can't contain images, but other
+    /// formats such as Shoutcast v2 streams can.
+    pub fn artwork(&self) -> &[Artwork] {
+        &self.artwork
+    }
+
+    /// Attributes parsed from a Triton-style `StreamTitle`, if the station sent one. In this case,
+    /// [`stream_title`](Self::stream_title) is replaced with `Artist - Title` and the original
+    /// value is available from [`TritonTitle::raw`].
+    pub fn triton(&self) -> Option<&TritonTitle> {
+        self.triton.as_ref()
+    }
 }
 
 impl FromStr for IcyMetadata {
     type Err = EmptyMetadataError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut metadata = Self {
-            stream_title: None,
-            stream_url: None,
-            custom: HashMap::new(),
-        };
+        let mut metadata = Self::default();
 
         let ParseResult {
             map,
@@ -314,7 +524,7 @@ impl FromStr for IcyMetadata {
                     metadata.stream_title = Some(value.to_string());
                 }
                 "streamurl" => {
-                    metadata.stream_url = Some(value.to_string());
+                    metadata.stream_url = Some(value.to_string().into());
                 }
                 _ => {
                     metadata.custom.insert(key.to_string(), value.to_string());
@@ -338,6 +548,7 @@ impl FromStr for IcyMetadata {
             }
         }
 
+        metadata.apply_triton();
         Ok(metadata)
     }
 }
@@ -376,7 +587,7 @@ fn handle_unescaped_values(s: &str, metadata: &mut IcyMetadata) {
     };
 
     if let Some(stream_url) = stream_url {
-        metadata.stream_url = parse_value_if_valid(stream_url);
+        metadata.stream_url = parse_value_if_valid(stream_url).map(Into::into);
     };
 }
 
@@ -0,0 +1,580 @@
+//! Records a stream to disk, splitting it into a separate file for each track.
+//!
+//! [`StreamRecorder`] wraps an [`IcyMetadataReader`] and starts a new file each time the
+//! `StreamTitle` changes. The audio is passed through unchanged, so the recorder can also be used
+//! as the input to a decoder while recording.
+//!
+//! Metadata blocks are inserted at fixed byte offsets, which usually fall in the middle of an audio
+//! frame. For MP3 and AAC streams, each track boundary is moved to the nearest frame boundary so
+//! every file starts with a complete frame.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::frame::{FrameAligner, FrameHeader, xing_frame};
+use crate::{IcyHeaders, IcyMetadata, IcyMetadataReader};
+
+// Most filesystems limit names to 255 bytes, leave some room for the suffix added to duplicates
+const MAX_NAME_LEN: usize = 200;
+// Anything after the last dot that's longer than this is part of the name rather than an extension
+const MAX_EXTENSION_LEN: usize = 16;
+
+/// Records each track in a stream to its own file.
+///
+/// File names are generated from a template. The following placeholders are supported:
+///
+/// - `{artist}` - the track artist
+/// - `{title}` - the track title
+/// - `{stream_title}` - the full `StreamTitle` value
+/// - `{index}` - the track number within the recording, starting at `001`
+///
+/// If the metadata wasn't sent in a structured format, the artist and title are taken from a
+/// `StreamTitle` in the form `Artist - Title`. Missing values are replaced with `Unknown`.
+/// Characters that aren't valid in file names are replaced with `_`.
+///
+/// Track boundaries are only as accurate as the metadata sent by the server, which is often a
+/// few seconds off. Use [`Self::pre_padding`] and [`Self::post_padding`] to include some extra
+/// audio around each boundary. Padding requires the stream bitrate, which is taken from the
+/// [`IcyHeaders`].
+///
+/// The audio received before the first metadata block is kept until the first track starts and
+/// is included at the start of its file.
+pub struct StreamRecorder<T> {
+    reader: IcyMetadataReader<T>,
+    headers: IcyHeaders,
+    latest_metadata: Arc<Mutex<Option<IcyMetadata>>>,
+    output_dir: PathBuf,
+    template: String,
+    bytes_per_second: Option<usize>,
+    pre_padding: usize,
+    post_padding: usize,
+    skip_first_track: bool,
+    align_frames: bool,
+    write_xing_header: bool,
+    write_id3_tags: bool,
+    aligner: FrameAligner,
+    pending_split: Option<PendingSplit>,
+    current_title: Option<String>,
+    track_count: usize,
+    current: Option<TrackFile>,
+    finishing: Option<TrackFile>,
+    history: VecDeque<(Option<FrameHeader>, Vec<u8>)>,
+    history_len: usize,
+    lead_in: Option<Vec<(Option<FrameHeader>, Vec<u8>)>>,
+}
+
+impl<T> Debug for StreamRecorder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamRecorder")
+            .field("reader", &self.reader)
+            .field("headers", &self.headers)
+            .field("latest_metadata", &self.latest_metadata)
+            .field("output_dir", &self.output_dir)
+            .field("template", &self.template)
+            .field("bytes_per_second", &self.bytes_per_second)
+            .field("pre_padding", &self.pre_padding)
+            .field("post_padding", &self.post_padding)
+            .field("skip_first_track", &self.skip_first_track)
+            .field("align_frames", &self.align_frames)
+            .field("write_xing_header", &self.write_xing_header)
+            .field("write_id3_tags", &self.write_id3_tags)
+            .field("aligner", &self.aligner)
+            .field("pending_split", &self.pending_split)
+            .field("current_title", &self.current_title)
+            .field("track_count", &self.track_count)
+            .field("current", &self.current)
+            .field("finishing", &self.finishing)
+            .field("history", &self.history.len())
+            .field("history_len", &self.history_len)
+            .field("lead_in", &self.lead_in.as_ref().map(Vec::len))
+            .finish()
+    }
+}
+
+/// Track change that will be applied once the remaining bytes before the boundary are written.
+#[derive(Debug)]
+struct PendingSplit {
+    remaining: usize,
+    metadata: IcyMetadata,
+    first_track: bool,
+}
+
+#[derive(Debug)]
+struct TrackFile {
+    path: PathBuf,
+    file: BufWriter<File>,
+    written: u64,
+    remaining: usize,
+    xing: Option<XingInfo>,
+}
+
+#[derive(Debug, Default)]
+struct XingInfo {
+    offset: u64,
+    first: Option<FrameHeader>,
+    frames: u32,
+    vbr: bool,
+}
+
+impl TrackFile {
+    fn create(path: PathBuf, write_xing_header: bool) -> io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(&path)?),
+            path,
+            written: 0,
+            remaining: 0,
+            xing: write_xing_header.then(XingInfo::default),
+        })
+    }
+
+    fn write(&mut self, header: Option<&FrameHeader>, data: &[u8]) -> io::Result<()> {
+        if let (Some(xing), Some(header)) = (&mut self.xing, header) {
+            if let Some(first) = &xing.first {
+                xing.vbr |= first.bitrate() != header.bitrate();
+            } else if let Some(frame) = xing_frame(header, 0, 0, false) {
+                // Write a placeholder that's filled in once the file is complete
+                xing.offset = self.written;
+                xing.first = Some(header.clone());
+                self.file.write_all(&frame)?;
+                self.written += frame.len() as u64;
+            }
+            if xing.first.is_some() {
+                xing.frames += 1;
+            }
+        }
+        self.file.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let Some(XingInfo {
+            offset,
+            first: Some(first),
+            frames,
+            vbr,
+        }) = self.xing.take()
+        else {
+            return Ok(());
+        };
+        let bytes = u32::try_from(self.written - offset).unwrap_or(u32::MAX);
+        if let Some(frame) = xing_frame(&first, frames, bytes, vbr) {
+            let file = self.file.get_mut();
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(&frame)?;
+            file.seek(SeekFrom::End(0))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TrackFile {
+    fn drop(&mut self) {
+        // Errors are ignored here, use finish() to handle them
+        let _ = self.finish();
+    }
+}
+
+impl<T> StreamRecorder<T> {
+    /// Creates a new `StreamRecorder` that writes files to `output_dir`.
+    /// `headers` must contain the metadata interval in order to find the track boundaries.
+    /// The default template is `{artist} - {title}.mp3`.
+    pub fn new<P>(inner: T, headers: &IcyHeaders, output_dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let latest_metadata = Arc::new(Mutex::new(None));
+        let reader = IcyMetadataReader::new(inner, headers.metadata_interval(), {
+            let latest_metadata = latest_metadata.clone();
+            move |metadata| {
+                if let Ok(metadata) = metadata {
+                    *latest_metadata
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner) = Some(metadata);
+                }
+            }
+        });
+        Self {
+            reader,
+            headers: headers.clone(),
+            latest_metadata,
+            output_dir: output_dir.into(),
+            template: "{artist} - {title}.mp3".to_string(),
+            bytes_per_second: headers.bitrate().map(|bitrate| bitrate as usize * 1000 / 8),
+            pre_padding: 0,
+            post_padding: 0,
+            skip_first_track: false,
+            align_frames: true,
+            write_xing_header: false,
+            write_id3_tags: false,
+            aligner: FrameAligner::default(),
+            pending_split: None,
+            current_title: None,
+            track_count: 0,
+            current: None,
+            finishing: None,
+            history: VecDeque::new(),
+            history_len: 0,
+            lead_in: Some(Vec::new()),
+        }
+    }
+
+    /// Set the template used to generate file names.
+    pub fn template<S>(mut self, template: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.template = template.into();
+        self
+    }
+
+    /// Set the amount of audio from before the track boundary to include at the start of each
+    /// file.
+    pub fn pre_padding(mut self, padding: Duration) -> Self {
+        self.pre_padding = self.padding_bytes(padding);
+        self
+    }
+
+    /// Set the amount of audio from after the track boundary to include at the end of each file.
+    pub fn post_padding(mut self, padding: Duration) -> Self {
+        self.post_padding = self.padding_bytes(padding);
+        self
+    }
+
+    /// Skip the track that was already playing when the recording started, since it will be
+    /// missing its beginning.
+    pub fn skip_first_track(mut self, skip_first_track: bool) -> Self {
+        self.skip_first_track = skip_first_track;
+        self
+    }
+
+    /// Move each track boundary to the nearest MP3 or AAC frame boundary so every file starts with
+    /// a complete frame. Streams that don't contain MP3 or AAC frames are split at the exact byte
+    /// offset of the metadata. Enabled by default.
+    pub fn align_frames(mut self, align_frames: bool) -> Self {
+        self.align_frames = align_frames;
+        self
+    }
+
+    /// Write a Xing header to the start of each MP3 file. The header contains the number of frames
+    /// in the file, which allows players to calculate the correct duration of VBR files. This
+    /// requires [`Self::align_frames`] to be enabled.
+    pub fn write_xing_header(mut self, write_xing_header: bool) -> Self {
+        self.write_xing_header = write_xing_header;
+        self
+    }
+
+    /// Write an ID3 tag to the start of each file. The tag contains the track's artist, title, and
+    /// artwork along with the station name and genre. See [`IcyMetadata::to_id3`] for details.
+    pub fn write_id3_tags(mut self, write_id3_tags: bool) -> Self {
+        self.write_id3_tags = write_id3_tags;
+        self
+    }
+
+    /// Path of the file currently being written, if any.
+    pub fn current_path(&self) -> Option<&Path> {
+        self.current.as_ref().map(|track| track.path.as_path())
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> IcyMetadataReader<T> {
+        self.reader
+    }
+
+    fn padding_bytes(&self, padding: Duration) -> usize {
+        self.bytes_per_second
+            .map(|bytes_per_second| (bytes_per_second as f64 * padding.as_secs_f64()) as usize)
+            .unwrap_or_default()
+    }
+
+    fn file_name(&self, metadata: &IcyMetadata) -> String {
+        let stream_title = metadata.stream_title().unwrap_or_default();
+        let (artist, title) = metadata.artist_and_title();
+        let name = self
+            .template
+            .replace("{artist}", &sanitize(artist.unwrap_or_default()))
+            .replace("{title}", &sanitize(title.unwrap_or_default()))
+            .replace("{stream_title}", &sanitize(stream_title))
+            .replace("{index}", &format!("{:03}", self.track_count));
+        truncate_file_name(&name)
+    }
+
+    fn on_metadata(&mut self, metadata: IcyMetadata) -> io::Result<()> {
+        let Some(title) = metadata.stream_title().filter(|title| !title.is_empty()) else {
+            return Ok(());
+        };
+        if self.current_title.as_deref() == Some(title) {
+            return Ok(());
+        }
+        let first_track = self.current_title.is_none();
+        self.current_title = Some(title.to_string());
+        // Only one split can be pending at a time
+        if let Some(split) = self.pending_split.take() {
+            self.split(&split.metadata, split.first_track)?;
+        }
+        if !self.align_frames {
+            return self.split(&metadata, first_track);
+        }
+        // The boundary is at the end of the data that hasn't been written yet. If that's inside a
+        // frame, move it to the start or end of the frame, whichever is closer.
+        let pending = self.aligner.pending_len();
+        let remaining = match self.aligner.pending_header() {
+            Some(header) if pending * 2 <= header.frame_len() => 0,
+            Some(header) => header.frame_len(),
+            None => pending,
+        };
+        self.pending_split = Some(PendingSplit {
+            remaining,
+            metadata,
+            first_track,
+        });
+        Ok(())
+    }
+
+    fn split(&mut self, metadata: &IcyMetadata, first_track: bool) -> io::Result<()> {
+        self.end_track()?;
+        if first_track && self.skip_first_track {
+            self.lead_in = None;
+            return Ok(());
+        }
+        self.start_track(metadata)
+    }
+
+    fn start_track(&mut self, metadata: &IcyMetadata) -> io::Result<()> {
+        self.track_count += 1;
+        fs::create_dir_all(&self.output_dir)?;
+        let path = unique_path(self.output_dir.join(self.file_name(metadata)));
+        let mut track = TrackFile::create(path, self.write_xing_header)?;
+        if self.write_id3_tags {
+            track.write(None, &metadata.to_id3(Some(&self.headers)))?;
+        }
+        // The lead-in already contains everything in the history
+        if let Some(lead_in) = self.lead_in.take() {
+            for (header, data) in &lead_in {
+                track.write(header.as_ref(), data)?;
+            }
+        } else {
+            for (header, data) in &self.history {
+                track.write(header.as_ref(), data)?;
+            }
+        }
+        self.current = Some(track);
+        Ok(())
+    }
+
+    fn end_track(&mut self) -> io::Result<()> {
+        if let Some(mut finishing) = self.finishing.take() {
+            finishing.finish()?;
+        }
+        if let Some(mut current) = self.current.take() {
+            if self.post_padding > 0 {
+                current.remaining = self.post_padding;
+                self.finishing = Some(current);
+            } else {
+                current.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_audio(&mut self, data: &[u8], eof: bool) -> io::Result<()> {
+        if !self.align_frames {
+            return self.write_unit(None, data);
+        }
+        self.aligner.push(data);
+        loop {
+            if let Some(split) = self.pending_split.take_if(|split| split.remaining == 0) {
+                self.split(&split.metadata, split.first_track)?;
+            }
+            let max_unaligned = self
+                .pending_split
+                .as_ref()
+                .map_or(usize::MAX, |split| split.remaining);
+            let Some((header, unit)) = self.aligner.next(eof, max_unaligned) else {
+                break;
+            };
+            if let Some(split) = &mut self.pending_split {
+                split.remaining = split.remaining.saturating_sub(unit.len());
+            }
+            self.write_unit(header.as_ref(), &unit)?;
+        }
+        if eof {
+            if let Some(split) = self.pending_split.take() {
+                self.split(&split.metadata, split.first_track)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_unit(&mut self, header: Option<&FrameHeader>, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if let Some(current) = &mut self.current {
+            current.write(header, data)?;
+        }
+        if let Some(finishing) = &mut self.finishing {
+            // Frames are written whole, unaligned data can be cut at the exact padding length
+            let len = if header.is_some() {
+                data.len()
+            } else {
+                finishing.remaining.min(data.len())
+            };
+            finishing.write(header, &data[..len])?;
+            finishing.remaining = finishing.remaining.saturating_sub(len);
+            if finishing.remaining == 0 {
+                finishing.finish()?;
+                self.finishing = None;
+            }
+        }
+        if let Some(lead_in) = &mut self.lead_in {
+            lead_in.push((header.cloned(), data.to_vec()));
+        }
+        if self.pre_padding > 0 {
+            self.history.push_back((header.cloned(), data.to_vec()));
+            self.history_len += data.len();
+            self.trim_history();
+        }
+        Ok(())
+    }
+
+    fn trim_history(&mut self) {
+        while let Some((header, data)) = self.history.front_mut() {
+            let excess = self.history_len.saturating_sub(self.pre_padding);
+            if excess == 0 {
+                break;
+            }
+            if data.len() <= excess {
+                self.history_len -= data.len();
+                self.history.pop_front();
+            } else if header.is_none() {
+                data.drain(..excess);
+                self.history_len -= excess;
+            } else {
+                // Keep the whole frame
+                break;
+            }
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        for track in [&mut self.current, &mut self.finishing]
+            .into_iter()
+            .flatten()
+        {
+            track.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> StreamRecorder<T>
+where
+    T: Read,
+{
+    /// Records until the end of the stream, discarding the passthrough audio.
+    pub fn record(&mut self) -> io::Result<()> {
+        io::copy(self, &mut io::sink())?;
+        Ok(())
+    }
+}
+
+impl<T> Read for StreamRecorder<T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Limit each read to a single metadata block so we know where the track boundary is.
+        // When a block is due, the reader handles it before any audio, so new metadata always
+        // applies to the start of the data that was just read.
+        let until_metadata = self.reader.bytes_until_metadata();
+        let len = match (until_metadata, self.reader.metadata_interval()) {
+            (Some(0), Some(metaint)) => buf.len().min(metaint),
+            (Some(remaining), _) => buf.len().min(remaining),
+            _ => buf.len(),
+        };
+        let read = self.reader.read(&mut buf[..len])?;
+        let metadata = self
+            .latest_metadata
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(metadata) = metadata {
+            self.on_metadata(metadata)?;
+        }
+        // Keep the lead-in only if the first metadata block started a track
+        if until_metadata == Some(0) && self.pending_split.is_none() && self.current.is_none() {
+            self.lead_in = None;
+        }
+        self.write_audio(&buf[..read], read == 0)?;
+        if read == 0 {
+            self.finish()?;
+        }
+        Ok(read)
+    }
+}
+
+/// Replaces characters that aren't valid in file names.
+pub(crate) fn sanitize(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows doesn't allow names that end with a dot or space
+    let sanitized = sanitized.trim().trim_end_matches('.').trim_end();
+    if sanitized.is_empty() {
+        "Unknown".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Shortens a file name to at most `MAX_NAME_LEN` bytes, keeping the extension.
+pub(crate) fn truncate_file_name(name: &str) -> String {
+    if name.len() <= MAX_NAME_LEN {
+        return name.to_string();
+    }
+    let (stem, extension) = match name.rfind('.') {
+        Some(i) if name.len() - i <= MAX_EXTENSION_LEN => name.split_at(i),
+        _ => (name, ""),
+    };
+    let mut end = MAX_NAME_LEN - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    // Windows doesn't allow names that end with a dot or space
+    let stem = stem[..end].trim_end_matches(['.', ' ']);
+    format!("{stem}{extension}")
+}
+
+fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut i = 2;
+    loop {
+        let candidate = path.with_file_name(format!("{stem} ({i}){extension}"));
+        if !candidate.exists() {
+            return candidate;
+        }
+        i += 1;
+    }
+}
@@ -0,0 +1,271 @@
+//! Icecast source client for pushing encoded audio to a mount point.
+//!
+//! The client speaks the HTTP `PUT` protocol used by Icecast 2.4 and newer and can fall back to
+//! the legacy `SOURCE` method used by older servers.
+//!
+//! The request body isn't framed. No `Content-Length` or `Transfer-Encoding` header is sent, and
+//! the audio is written directly to the connection until it's closed, which is how Icecast and
+//! libshout handle source connections.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use http::{HeaderMap, HeaderValue, StatusCode, header};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::IcyHeaders;
+use crate::error::SourceError;
+use crate::http_head::{basic_auth, read_response_head_async, write_request_head};
+
+/// HTTP method used to start the source connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceMethod {
+    /// Try `PUT` first and reconnect using `SOURCE` if the server doesn't support it.
+    #[default]
+    Auto,
+    /// HTTP `PUT`, supported by Icecast 2.4 and newer.
+    Put,
+    /// The legacy `SOURCE` method.
+    Source,
+}
+
+/// Client for sending a stream to an Icecast mount point.
+#[derive(Clone, Debug)]
+pub struct SourceClient {
+    host: String,
+    port: u16,
+    mount: String,
+    username: String,
+    password: String,
+    content_type: String,
+    icy_headers: IcyHeaders,
+    method: SourceMethod,
+    expect_continue: bool,
+    user_agent: String,
+}
+
+impl SourceClient {
+    /// Creates a new `SourceClient` that will send a stream to `mount` on the given server.
+    /// Defaults to the `source` user with an empty password and an `audio/mpeg` content type.
+    pub fn new<H, M>(host: H, port: u16, mount: M) -> Self
+    where
+        H: Into<String>,
+        M: Into<String>,
+    {
+        let mount = mount.into();
+        let mount = if mount.starts_with('/') {
+            mount
+        } else {
+            format!("/{mount}")
+        };
+        Self {
+            host: host.into(),
+            port,
+            mount,
+            username: "source".to_string(),
+            password: String::new(),
+            content_type: "audio/mpeg".to_string(),
+            icy_headers: IcyHeaders::default(),
+            method: SourceMethod::default(),
+            expect_continue: true,
+            user_agent: format!("icy-metadata/{}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// Set the credentials used to authenticate with the server.
+    pub fn credentials<U, P>(mut self, username: U, password: P) -> Self
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.username = username.into();
+        self.password = password.into();
+        self
+    }
+
+    /// Set the `Content-Type` of the stream.
+    pub fn content_type<S>(mut self, content_type: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// Set the stream information sent to the server as `ice-*` headers.
+    pub fn icy_headers(mut self, icy_headers: IcyHeaders) -> Self {
+        self.icy_headers = icy_headers;
+        self
+    }
+
+    /// Set the HTTP method used to start the connection.
+    pub fn method(mut self, method: SourceMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set whether to send `Expect: 100-continue` with `PUT` requests. When it's sent, Icecast
+    /// replies with `100 Continue` instead of a final status, so the source starts as soon as
+    /// that's received. Enabled by default.
+    pub fn expect_continue(mut self, expect_continue: bool) -> Self {
+        self.expect_continue = expect_continue;
+        self
+    }
+
+    /// Set the `User-Agent` sent to the server.
+    pub fn user_agent<S>(mut self, user_agent: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Connects to the server and starts the source. When using [`SourceMethod::Auto`], a second
+    /// connection will be made with the `SOURCE` method if the server rejects the `PUT` request.
+    pub async fn connect(&self) -> Result<SourceStream<TcpStream>, SourceError> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        match self.handshake(stream).await {
+            Err(SourceError::MethodNotAllowed) if self.method == SourceMethod::Auto => {
+                let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+                self.start(stream, SourceMethod::Source).await
+            }
+            res => res,
+        }
+    }
+
+    /// Starts the source over an existing connection. This can be used to supply your own
+    /// transport, such as a TLS stream. Since there's no way to reconnect,
+    /// [`SourceMethod::Auto`] is treated the same as [`SourceMethod::Put`].
+    pub async fn handshake<S>(&self, stream: S) -> Result<SourceStream<S>, SourceError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.start(stream, self.method).await
+    }
+
+    async fn start<S>(
+        &self,
+        mut stream: S,
+        method: SourceMethod,
+    ) -> Result<SourceStream<S>, SourceError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        // Expect: 100-continue is only defined for HTTP/1.1, so PUT requests are sent as 1.1.
+        // SOURCE predates it and is always sent as 1.0.
+        let (method_name, version) = if method == SourceMethod::Source {
+            ("SOURCE", "HTTP/1.0")
+        } else {
+            ("PUT", "HTTP/1.1")
+        };
+        let expect_continue = self.expect_continue && method != SourceMethod::Source;
+        let head = write_request_head(
+            method_name,
+            &self.mount,
+            version,
+            &self.request_headers(expect_continue),
+        );
+        stream.write_all(&head).await?;
+        stream.flush().await?;
+
+        loop {
+            let response = read_response_head_async(&mut stream).await?;
+            match response.status {
+                // Icecast replies to Expect with "100 Continue" alone and then waits for the body
+                status if status.is_informational() && expect_continue => {
+                    return Ok(SourceStream { inner: stream });
+                }
+                status if status.is_informational() => continue,
+                status if status.is_success() => return Ok(SourceStream { inner: stream }),
+                StatusCode::UNAUTHORIZED => return Err(SourceError::Unauthorized),
+                StatusCode::FORBIDDEN => return Err(SourceError::Forbidden(response.reason)),
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => {
+                    return Err(SourceError::MethodNotAllowed);
+                }
+                status => {
+                    return Err(SourceError::UnexpectedStatus {
+                        status: status.as_u16(),
+                        reason: response.reason,
+                    });
+                }
+            }
+        }
+    }
+
+    fn request_headers(&self, expect_continue: bool) -> HeaderMap {
+        let mut headers = self.icy_headers.to_header_map();
+        let mut insert = |name, value: &str| {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.insert(name, value);
+            }
+        };
+        insert(header::HOST, &format!("{}:{}", self.host, self.port));
+        insert(
+            header::AUTHORIZATION,
+            &basic_auth(&self.username, &self.password),
+        );
+        insert(header::USER_AGENT, &self.user_agent);
+        insert(header::CONTENT_TYPE, &self.content_type);
+        if expect_continue {
+            insert(header::EXPECT, "100-continue");
+        }
+        headers
+    }
+}
+
+/// An established source connection. Anything written to the stream is sent to the mount point.
+#[derive(Debug)]
+pub struct SourceStream<S> {
+    inner: S,
+}
+
+impl<S> SourceStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    /// Copies the entire contents of `reader` to the server, returning the number of bytes sent.
+    pub async fn send<R>(&mut self, reader: &mut R) -> io::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let sent = tokio::io::copy(reader, &mut self.inner).await?;
+        self.inner.flush().await?;
+        Ok(sent)
+    }
+}
+
+impl<S> SourceStream<S> {
+    /// Gets a reference to the underlying connection.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes the `SourceStream`, returning the underlying connection.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> AsyncWrite for SourceStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
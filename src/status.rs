@@ -0,0 +1,795 @@
+//! Parsing for the status pages that streaming servers expose.
+//!
+//! These are useful for displaying what's currently playing without connecting to the stream.
+//! Icecast publishes its status at `/status-json.xsl` and, for authenticated admins,
+//! `/admin/stats`. Both formats are parsed into an [`IcecastStatus`].
+//!
+//! Shoutcast publishes its status at `/stats?sid=1` in either XML or JSON format, along with the
+//! legacy `/7.html` page. These are parsed into a [`ShoutcastStats`]. Recently played songs are
+//! available from `/played.html` and are parsed into a [`SongHistory`].
+
+use std::borrow::Cow;
+use std::time::{Duration, SystemTime};
+
+use http::Uri;
+
+use crate::error::InvalidStatusError;
+use crate::xml::Element;
+use crate::{IcyHeaders, IcyMetadata};
+
+/// Server status returned from Icecast's `/status-json.xsl` or `/admin/stats` endpoints.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcecastStatus {
+    admin: Option<String>,
+    host: Option<String>,
+    location: Option<String>,
+    server_id: Option<String>,
+    server_start: Option<String>,
+    sources: Vec<IcecastSource>,
+}
+
+/// Status of a single mount point.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcecastSource {
+    mount: Option<String>,
+    listen_url: Option<String>,
+    server_name: Option<String>,
+    server_description: Option<String>,
+    server_type: Option<String>,
+    server_url: Option<String>,
+    genre: Option<String>,
+    title: Option<String>,
+    artist: Option<String>,
+    bitrate: Option<u32>,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
+    quality: Option<String>,
+    audio_info: Option<String>,
+    listeners: Option<u32>,
+    listener_peak: Option<u32>,
+    stream_start: Option<String>,
+    public: Option<bool>,
+}
+
+impl IcecastStatus {
+    /// Parses the response from `/status-json.xsl`.
+    ///
+    /// Icecast sends a single object instead of an array when only one source is connected, and
+    /// omits the field entirely when there are no sources. All of these cases are handled.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, InvalidStatusError> {
+        use serde_json::Value;
+
+        let root: Value =
+            serde_json::from_str(json).map_err(|e| InvalidStatusError(e.to_string()))?;
+        let stats = root
+            .get("icestats")
+            .ok_or_else(|| InvalidStatusError("missing icestats object".to_string()))?;
+        let field = |value: &Value, name: &str| value.get(name).and_then(json_string);
+        let sources = match stats.get("source") {
+            Some(Value::Array(sources)) => sources.iter().collect(),
+            Some(source @ Value::Object(_)) => vec![source],
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            admin: field(stats, "admin"),
+            host: field(stats, "host"),
+            location: field(stats, "location"),
+            server_id: field(stats, "server_id"),
+            server_start: field(stats, "server_start_iso8601")
+                .or_else(|| field(stats, "server_start")),
+            sources: sources
+                .into_iter()
+                .map(|source| IcecastSource::from_fields(|name: &str| field(source, name), None))
+                .collect(),
+        })
+    }
+
+    /// Parses the response from `/admin/stats`.
+    pub fn from_xml(xml: &str) -> Result<Self, InvalidStatusError> {
+        let root = Element::parse(xml).map_err(|e| InvalidStatusError(e.to_string()))?;
+        if !root.name.eq_ignore_ascii_case("icestats") {
+            return Err(InvalidStatusError(format!(
+                "expected icestats element, found {}",
+                root.name
+            )));
+        }
+        let field = |name| root.child_text(name).map(str::to_string);
+        Ok(Self {
+            admin: field("admin"),
+            host: field("host"),
+            location: field("location"),
+            server_id: field("server_id"),
+            server_start: field("server_start_iso8601").or_else(|| field("server_start")),
+            sources: root
+                .children("source")
+                .map(|source| {
+                    IcecastSource::from_fields(
+                        |name: &str| source.child_text(name).map(str::to_string),
+                        source.attribute("mount"),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Server admin contact.
+    pub fn admin(&self) -> Option<&str> {
+        self.admin.as_deref()
+    }
+
+    /// Server hostname.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Server location.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Server software and version, ex: `Icecast 2.4.4`.
+    pub fn server_id(&self) -> Option<&str> {
+        self.server_id.as_deref()
+    }
+
+    /// When the server was started. This is in ISO 8601 format if the server provides it.
+    pub fn server_start(&self) -> Option<&str> {
+        self.server_start.as_deref()
+    }
+
+    /// All connected sources.
+    pub fn sources(&self) -> &[IcecastSource] {
+        &self.sources
+    }
+
+    /// Finds the source for the given mount point. The leading `/` is optional.
+    pub fn source(&self, mount: &str) -> Option<&IcecastSource> {
+        let mount = mount.trim_start_matches('/');
+        self.sources
+            .iter()
+            .find(|source| source.mount().map(|m| m.trim_start_matches('/')) == Some(mount))
+    }
+}
+
+impl IcecastSource {
+    fn from_fields<F>(field: F, mount: Option<&str>) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let number = |name| field(name).and_then(|value| value.trim().parse().ok());
+        let listen_url = field("listenurl");
+        Self {
+            // The JSON status doesn't include the mount, but it's always the path of the listen URL
+            mount: mount.map(str::to_string).or_else(|| {
+                listen_url
+                    .as_deref()
+                    .and_then(|url| url.parse::<Uri>().ok())
+                    .map(|url| url.path().to_string())
+            }),
+            listen_url,
+            server_name: field("server_name"),
+            server_description: field("server_description"),
+            server_type: field("server_type"),
+            server_url: field("server_url"),
+            genre: field("genre"),
+            title: field("title"),
+            artist: field("artist"),
+            bitrate: number("bitrate").or_else(|| number("ice-bitrate")),
+            sample_rate: number("samplerate").or_else(|| number("ice-samplerate")),
+            channels: field("channels")
+                .or_else(|| field("ice-channels"))
+                .and_then(|value| value.trim().parse().ok()),
+            quality: field("quality").or_else(|| field("ice-quality")),
+            audio_info: field("audio_info"),
+            listeners: number("listeners"),
+            listener_peak: number("listener_peak"),
+            stream_start: field("stream_start_iso8601").or_else(|| field("stream_start")),
+            public: field("public").map(|public| public == "1" || public == "true"),
+        }
+    }
+
+    /// Mount point, ex: `/stream`.
+    pub fn mount(&self) -> Option<&str> {
+        self.mount.as_deref()
+    }
+
+    /// URL that listeners connect to.
+    pub fn listen_url(&self) -> Option<&str> {
+        self.listen_url.as_deref()
+    }
+
+    /// Station name.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Station description.
+    pub fn server_description(&self) -> Option<&str> {
+        self.server_description.as_deref()
+    }
+
+    /// Content type of the stream, ex: `audio/mpeg`.
+    pub fn server_type(&self) -> Option<&str> {
+        self.server_type.as_deref()
+    }
+
+    /// Station website.
+    pub fn server_url(&self) -> Option<&str> {
+        self.server_url.as_deref()
+    }
+
+    /// Station genre.
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    /// Currently playing title. If [`artist`](Self::artist) isn't set, this usually contains both
+    /// the artist and title.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Currently playing artist. Only set if the source sends the artist separately.
+    pub fn artist(&self) -> Option<&str> {
+        self.artist.as_deref()
+    }
+
+    /// Stream bitrate in kbps.
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bitrate
+    }
+
+    /// Stream sample rate.
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    /// Number of audio channels.
+    pub fn channels(&self) -> Option<u16> {
+        self.channels
+    }
+
+    /// Encoding quality.
+    pub fn quality(&self) -> Option<&str> {
+        self.quality.as_deref()
+    }
+
+    /// Raw audio info string, in the same format as the `ice-audio-info` header.
+    pub fn audio_info(&self) -> Option<&str> {
+        self.audio_info.as_deref()
+    }
+
+    /// Current number of listeners.
+    pub fn listeners(&self) -> Option<u32> {
+        self.listeners
+    }
+
+    /// Highest number of concurrent listeners since the source connected.
+    pub fn listener_peak(&self) -> Option<u32> {
+        self.listener_peak
+    }
+
+    /// When the source connected. This is in ISO 8601 format if the server provides it.
+    pub fn stream_start(&self) -> Option<&str> {
+        self.stream_start.as_deref()
+    }
+
+    /// Whether the stream is listed in public directories.
+    pub fn public(&self) -> Option<bool> {
+        self.public
+    }
+}
+
+impl From<&IcecastSource> for IcyHeaders {
+    fn from(source: &IcecastSource) -> Self {
+        let mut headers = Self::default();
+        if let Some(audio_info) = &source.audio_info {
+            headers = headers.with_audio_info(audio_info);
+        }
+        if let Some(name) = &source.server_name {
+            headers = headers.with_name(name);
+        }
+        if let Some(description) = &source.server_description {
+            headers = headers.with_description(description);
+        }
+        if let Some(url) = &source.server_url {
+            headers = headers.with_station_url(url);
+        }
+        if let Some(genre) = &source.genre {
+            headers = headers.with_genre(genre.split(',').map(str::trim));
+        }
+        if let Some(bitrate) = source.bitrate {
+            headers = headers.with_bitrate(bitrate);
+        }
+        if let Some(sample_rate) = source.sample_rate {
+            headers = headers.with_sample_rate(sample_rate);
+        }
+        if let Some(channels) = source.channels {
+            headers = headers.with_channels(channels);
+        }
+        if let Some(quality) = &source.quality {
+            headers = headers.with_quality(quality);
+        }
+        if let Some(public) = source.public {
+            headers = headers.with_public(public);
+        }
+        headers
+    }
+}
+
+impl From<&IcecastSource> for IcyMetadata {
+    fn from(source: &IcecastSource) -> Self {
+        let mut metadata = Self::default();
+        if source.artist.is_some() {
+            metadata.artist.clone_from(&source.artist);
+            metadata.title.clone_from(&source.title);
+        } else {
+            metadata.stream_title.clone_from(&source.title);
+        }
+        metadata.fill_stream_title();
+        metadata
+    }
+}
+
+/// Server status returned from Shoutcast's `/stats` endpoint or the legacy `/7.html` page.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShoutcastStats {
+    current_listeners: Option<u32>,
+    peak_listeners: Option<u32>,
+    max_listeners: Option<u32>,
+    unique_listeners: Option<u32>,
+    stream_status: Option<bool>,
+    server_title: Option<String>,
+    server_url: Option<String>,
+    genre: Vec<String>,
+    song_title: Option<String>,
+    next_title: Option<String>,
+    stream_path: Option<String>,
+    content_type: Option<String>,
+    bitrate: Option<u32>,
+    sample_rate: Option<u32>,
+    version: Option<String>,
+    song_history: SongHistory,
+}
+
+impl ShoutcastStats {
+    /// Parses the response from `/stats?sid=1&json=1`.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, InvalidStatusError> {
+        let root: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| InvalidStatusError(e.to_string()))?;
+        if !root.is_object() {
+            return Err(InvalidStatusError("expected a JSON object".to_string()));
+        }
+        Ok(Self::from_fields(|name: &str| {
+            root.get(name).and_then(json_string)
+        }))
+    }
+
+    /// Parses the response from `/stats?sid=1` or the v1 `/admin.cgi?mode=viewxml` page. The v1
+    /// page also includes the song history.
+    pub fn from_xml(xml: &str) -> Result<Self, InvalidStatusError> {
+        let root = Element::parse(xml).map_err(|e| InvalidStatusError(e.to_string()))?;
+        if !root.name.eq_ignore_ascii_case("shoutcastserver") {
+            return Err(InvalidStatusError(format!(
+                "expected SHOUTCASTSERVER element, found {}",
+                root.name
+            )));
+        }
+        let mut stats = Self::from_fields(|name: &str| root.child_text(name).map(str::to_string));
+        if let Some(history) = root.child("songhistory") {
+            stats.song_history.songs = history
+                .children("song")
+                .filter_map(|song| {
+                    Some(PlayedSong {
+                        played_at: PlayedAt::from_timestamp(
+                            song.child_text("playedat")?.parse().ok()?,
+                        )?,
+                        title: song.child_text("title")?.to_string(),
+                    })
+                })
+                .collect();
+        }
+        Ok(stats)
+    }
+
+    /// Parses the legacy `/7.html` page, ex: `<html><body>4,1,10,100,3,128,Title</body></html>`.
+    ///
+    /// The page contains the current listeners, stream status, peak listeners, max listeners,
+    /// unique listeners, bitrate, and song title.
+    pub fn from_7_html(html: &str) -> Result<Self, InvalidStatusError> {
+        let text = html_text(html);
+        // The title may contain commas, so it's everything after the sixth field
+        let fields: Vec<_> = text.splitn(7, ',').map(str::trim).collect();
+        let [listeners, status, peak, max, unique, bitrate, title] = fields[..] else {
+            return Err(InvalidStatusError(format!(
+                "expected 7 fields, found {text}"
+            )));
+        };
+        Ok(Self {
+            current_listeners: listeners.parse().ok(),
+            stream_status: Some(status == "1"),
+            peak_listeners: peak.parse().ok(),
+            max_listeners: max.parse().ok(),
+            unique_listeners: unique.parse().ok(),
+            bitrate: bitrate.parse().ok(),
+            song_title: (!title.is_empty()).then(|| title.to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn from_fields<F>(field: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let number = |name| field(name).and_then(|value| value.trim().parse().ok());
+        Self {
+            current_listeners: number("currentlisteners"),
+            peak_listeners: number("peaklisteners"),
+            max_listeners: number("maxlisteners"),
+            unique_listeners: number("uniquelisteners"),
+            stream_status: field("streamstatus").map(|status| status == "1"),
+            server_title: field("servertitle"),
+            server_url: field("serverurl"),
+            genre: [
+                "servergenre",
+                "servergenre2",
+                "servergenre3",
+                "servergenre4",
+                "servergenre5",
+            ]
+            .into_iter()
+            .filter_map(&field)
+            .collect(),
+            song_title: field("songtitle"),
+            next_title: field("nexttitle"),
+            stream_path: field("streampath"),
+            content_type: field("content"),
+            bitrate: number("bitrate"),
+            sample_rate: number("samplerate"),
+            version: field("version"),
+            song_history: SongHistory::default(),
+        }
+    }
+
+    /// Current number of listeners.
+    pub fn current_listeners(&self) -> Option<u32> {
+        self.current_listeners
+    }
+
+    /// Highest number of concurrent listeners.
+    pub fn peak_listeners(&self) -> Option<u32> {
+        self.peak_listeners
+    }
+
+    /// Maximum number of listeners the server allows.
+    pub fn max_listeners(&self) -> Option<u32> {
+        self.max_listeners
+    }
+
+    /// Number of unique listeners.
+    pub fn unique_listeners(&self) -> Option<u32> {
+        self.unique_listeners
+    }
+
+    /// Whether a source is connected.
+    pub fn stream_status(&self) -> Option<bool> {
+        self.stream_status
+    }
+
+    /// Station name.
+    pub fn server_title(&self) -> Option<&str> {
+        self.server_title.as_deref()
+    }
+
+    /// Station website.
+    pub fn server_url(&self) -> Option<&str> {
+        self.server_url.as_deref()
+    }
+
+    /// Station genres.
+    pub fn genre(&self) -> &[String] {
+        &self.genre
+    }
+
+    /// Currently playing song.
+    pub fn song_title(&self) -> Option<&str> {
+        self.song_title.as_deref()
+    }
+
+    /// Next song, if the source provides it.
+    pub fn next_title(&self) -> Option<&str> {
+        self.next_title.as_deref()
+    }
+
+    /// Path that listeners connect to, ex: `/stream`.
+    pub fn stream_path(&self) -> Option<&str> {
+        self.stream_path.as_deref()
+    }
+
+    /// Content type of the stream, ex: `audio/mpeg`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Stream bitrate in kbps.
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bitrate
+    }
+
+    /// Stream sample rate.
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    /// Server version.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Recently played songs. This is only included in the v1 XML stats.
+    pub fn song_history(&self) -> &SongHistory {
+        &self.song_history
+    }
+}
+
+impl From<&ShoutcastStats> for IcyHeaders {
+    fn from(stats: &ShoutcastStats) -> Self {
+        let mut headers = Self::default().with_genre(&stats.genre);
+        if let Some(name) = &stats.server_title {
+            headers = headers.with_name(name);
+        }
+        if let Some(url) = &stats.server_url {
+            headers = headers.with_station_url(url);
+        }
+        if let Some(bitrate) = stats.bitrate {
+            headers = headers.with_bitrate(bitrate);
+        }
+        if let Some(sample_rate) = stats.sample_rate {
+            headers = headers.with_sample_rate(sample_rate);
+        }
+        headers
+    }
+}
+
+impl From<&ShoutcastStats> for IcyMetadata {
+    fn from(stats: &ShoutcastStats) -> Self {
+        let mut metadata = Self::default();
+        metadata.stream_title.clone_from(&stats.song_title);
+        // Match the extension titles sent with Shoutcast v2 XML metadata
+        if let (Some(current), Some(next)) = (&stats.song_title, &stats.next_title) {
+            metadata.extension_titles = vec![current.clone(), next.clone()];
+        }
+        metadata
+    }
+}
+
+/// Songs that were recently played on a Shoutcast server, with the most recent song first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SongHistory {
+    songs: Vec<PlayedSong>,
+}
+
+impl SongHistory {
+    /// Parses the legacy `/played.html` page. The page only includes the time of day for each
+    /// song, in the server's time zone.
+    pub fn from_html(html: &str) -> Result<Self, InvalidStatusError> {
+        let lowercase = html.to_ascii_lowercase();
+        let mut songs = Vec::new();
+        for row in split_at_tag(html, &lowercase, "<tr") {
+            let lowercase_row = row.to_ascii_lowercase();
+            let cells: Vec<_> = split_at_tag(row, &lowercase_row, "<td")
+                .map(html_text)
+                .collect();
+            // The header row won't have a valid time
+            let [played_at, title, ..] = &cells[..] else {
+                continue;
+            };
+            if let (Some(played_at), false) =
+                (PlayedAt::from_time_of_day(played_at), title.is_empty())
+            {
+                songs.push(PlayedSong {
+                    played_at,
+                    title: title.clone(),
+                });
+            }
+        }
+        if songs.is_empty() && !lowercase.contains("<table") {
+            return Err(InvalidStatusError(
+                "no song history table found".to_string(),
+            ));
+        }
+        Ok(Self { songs })
+    }
+
+    /// Parses the response from `/played?sid=1&type=json`.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, InvalidStatusError> {
+        let root: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| InvalidStatusError(e.to_string()))?;
+        let songs = root
+            .as_array()
+            .ok_or_else(|| InvalidStatusError("expected a JSON array".to_string()))?
+            .iter()
+            .filter_map(|song| {
+                Some(PlayedSong {
+                    played_at: PlayedAt::from_timestamp(song.get("playedat")?.as_u64()?)?,
+                    title: song.get("title").and_then(json_string)?,
+                })
+            })
+            .collect();
+        Ok(Self { songs })
+    }
+
+    /// All songs in the history.
+    pub fn songs(&self) -> &[PlayedSong] {
+        &self.songs
+    }
+
+    /// Converts songs that only have a time of day into timestamps using
+    /// [`PlayedAt::to_timestamp`]. `fetched_at` should be when the history was retrieved and
+    /// `utc_offset_secs` the offset of the server's time zone from UTC. Songs that can't be
+    /// converted are removed.
+    pub fn with_timestamps(&self, fetched_at: SystemTime, utc_offset_secs: i32) -> Self {
+        let songs = self
+            .songs
+            .iter()
+            .filter_map(|song| {
+                Some(PlayedSong {
+                    played_at: PlayedAt::Timestamp(
+                        song.played_at.to_timestamp(fetched_at, utc_offset_secs)?,
+                    ),
+                    title: song.title.clone(),
+                })
+            })
+            .collect();
+        Self { songs }
+    }
+
+    /// Songs that started before `time`, with the most recent song first. This is useful for
+    /// backfilling the tracks that played before the listener connected. Songs that only have a
+    /// time of day are skipped, use [`with_timestamps`](Self::with_timestamps) to include them.
+    pub fn played_before(&self, time: SystemTime) -> impl Iterator<Item = &PlayedSong> {
+        self.songs.iter().filter(move |song| match song.played_at {
+            PlayedAt::Timestamp(played_at) => played_at < time,
+            PlayedAt::TimeOfDay(_) => false,
+        })
+    }
+}
+
+/// Song from a [`SongHistory`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayedSong {
+    played_at: PlayedAt,
+    title: String,
+}
+
+impl PlayedSong {
+    /// When the song started playing.
+    pub fn played_at(&self) -> PlayedAt {
+        self.played_at
+    }
+
+    /// Song title, usually in the form `Artist - Title`.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+impl From<&PlayedSong> for IcyMetadata {
+    fn from(song: &PlayedSong) -> Self {
+        Self::default().with_stream_title(song.title.clone())
+    }
+}
+
+/// When a [`PlayedSong`] started playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlayedAt {
+    /// Exact time the song started.
+    Timestamp(SystemTime),
+    /// Time since midnight in the server's time zone. The legacy HTML page doesn't include the
+    /// date.
+    TimeOfDay(Duration),
+}
+
+impl PlayedAt {
+    /// Returns the time the song started. A time of day is resolved to its most recent
+    /// occurrence at or before `reference` in a time zone `utc_offset_secs` seconds ahead of UTC.
+    /// Returns `None` if the result can't be represented as a `SystemTime`.
+    pub fn to_timestamp(self, reference: SystemTime, utc_offset_secs: i32) -> Option<SystemTime> {
+        const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+        let time_of_day = match self {
+            Self::Timestamp(time) => return Some(time),
+            Self::TimeOfDay(time_of_day) => i64::try_from(time_of_day.as_secs()).ok()?,
+        };
+        let reference = reference.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+        let offset = i64::from(utc_offset_secs);
+        let local = i64::try_from(reference.as_secs())
+            .ok()?
+            .checked_add(offset)?;
+        let mut played = (local - local.rem_euclid(SECS_PER_DAY)).checked_add(time_of_day)?;
+        if played > local {
+            played -= SECS_PER_DAY;
+        }
+        let played = u64::try_from(played.checked_sub(offset)?).ok()?;
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(played))
+    }
+
+    /// Returns `None` if the timestamp can't be represented as a `SystemTime`.
+    fn from_timestamp(timestamp: u64) -> Option<Self> {
+        SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_secs(timestamp))
+            .map(Self::Timestamp)
+    }
+
+    fn from_time_of_day(time: &str) -> Option<Self> {
+        let mut parts = time.trim().split(':').map(|part| part.parse::<u64>().ok());
+        let (Some(Some(hours)), Some(Some(minutes)), Some(Some(seconds)), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        if hours >= 24 || minutes >= 60 || seconds >= 60 {
+            return None;
+        }
+        Some(Self::TimeOfDay(Duration::from_secs(
+            hours * 3600 + minutes * 60 + seconds,
+        )))
+    }
+}
+
+/// Splits `html` at each occurrence of `tag`, skipping anything before the first one.
+/// `lowercase` must be `html` converted to lowercase so tags can be matched case-insensitively.
+fn split_at_tag<'a>(
+    html: &'a str,
+    lowercase: &'a str,
+    tag: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    let starts: Vec<_> = lowercase.match_indices(tag).map(|(i, _)| i).collect();
+    let ends: Vec<_> = starts.iter().skip(1).copied().chain([html.len()]).collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(move |(start, end)| &html[start..end])
+}
+
+/// Strips tags and decodes entities.
+fn html_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.trim();
+    quick_xml::escape::unescape(text)
+        .unwrap_or(Cow::Borrowed(text))
+        .into_owned()
+}
+
+/// Converts a JSON value to a string. Icecast and Shoutcast don't consistently quote values that
+/// look like numbers, so fields such as the title can be sent as numbers.
+#[cfg(feature = "serde")]
+fn json_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(value) => {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        }
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        serde_json::Value::Bool(value) => Some(if *value { "1" } else { "0" }.to_string()),
+        _ => None,
+    }
+}
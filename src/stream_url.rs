@@ -0,0 +1,218 @@
+//! Interpretation of the `StreamUrl` metadata value.
+//!
+//! There's no standard for what `StreamUrl` contains. Depending on the station, it could be a link
+//! to album art, the station's website, a query string with track information such as
+//! `artist=...&title=...`, or even a JSON object. [`StreamUrlKind`] classifies the value and
+//! extracts the track information where possible.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+// File extensions of URLs that link directly to an image
+const IMAGE_EXTENSIONS: [&str; 7] = [".jpg", ".jpeg", ".png", ".gif", ".webp", ".bmp", ".svg"];
+const ARTIST_KEYS: [&str; 2] = ["artist", "performer"];
+const TITLE_KEYS: [&str; 3] = ["title", "song", "track"];
+const ALBUM_KEYS: [&str; 1] = ["album"];
+const ARTWORK_KEYS: [&str; 10] = [
+    "artwork",
+    "artworkurl",
+    "artwork_url",
+    "image",
+    "imageurl",
+    "image_url",
+    "cover",
+    "coverurl",
+    "cover_url",
+    "albumart",
+];
+
+/// What a `StreamUrl` value contains.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StreamUrlKind {
+    /// Link to an image, usually the album art for the current track.
+    Artwork,
+    /// Link to a web page, such as the station's website.
+    Website,
+    /// Query string containing track information, ex: `artist=Artist&title=Title`.
+    Query(StreamUrlFields),
+    /// JSON object containing track information. This is only detected when the `serde` feature
+    /// is enabled.
+    Json(StreamUrlFields),
+    /// Anything else.
+    Unknown,
+}
+
+impl StreamUrlKind {
+    /// Classifies a `StreamUrl` value.
+    pub fn parse(stream_url: &str) -> Self {
+        let stream_url = stream_url.trim();
+        #[cfg(feature = "serde")]
+        if stream_url.starts_with('{') {
+            if let Some(fields) = StreamUrlFields::from_json(stream_url) {
+                return Self::Json(fields);
+            }
+        }
+
+        let lower = stream_url.to_ascii_lowercase();
+        let is_web_url = lower.starts_with("http://") || lower.starts_with("https://");
+        // Image links often have query parameters of their own, ex: `cover.jpg?title=...`
+        let path = lower.split(['?', '#']).next().unwrap_or_default();
+        if is_web_url
+            && IMAGE_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+        {
+            return Self::Artwork;
+        }
+        let query = if is_web_url {
+            stream_url.split_once('?').map(|(_, query)| query)
+        } else {
+            Some(stream_url)
+        };
+        if let Some(fields) = query.and_then(StreamUrlFields::from_query) {
+            return Self::Query(fields);
+        }
+        if is_web_url {
+            Self::Website
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Track information contained in the value, if any.
+    pub fn fields(&self) -> Option<&StreamUrlFields> {
+        match self {
+            Self::Query(fields) | Self::Json(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+/// Track information extracted from a `StreamUrl` query string or JSON object.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamUrlFields {
+    fields: BTreeMap<String, String>,
+}
+
+impl StreamUrlFields {
+    /// Parses a query string. Returns `None` if it doesn't contain any track information.
+    fn from_query(query: &str) -> Option<Self> {
+        let fields = query
+            .split('&')
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                let decode = |value: &str| {
+                    let value = value.replace('+', " ");
+                    urlencoding::decode(&value)
+                        .map(Cow::into_owned)
+                        .unwrap_or(value)
+                };
+                Some((decode(key).trim().to_ascii_lowercase(), decode(value)))
+            })
+            .collect();
+        Self { fields }.non_empty()
+    }
+
+    /// Parses a JSON object. Only top-level string and number values are kept.
+    #[cfg(feature = "serde")]
+    fn from_json(json: &str) -> Option<Self> {
+        let serde_json::Value::Object(object) = serde_json::from_str(json).ok()? else {
+            return None;
+        };
+        let fields = object
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value,
+                    serde_json::Value::Number(value) => value.to_string(),
+                    _ => return None,
+                };
+                Some((key.to_ascii_lowercase(), value))
+            })
+            .collect();
+        Self { fields }.non_empty()
+    }
+
+    fn non_empty(self) -> Option<Self> {
+        [
+            &ARTIST_KEYS[..],
+            &TITLE_KEYS[..],
+            &ALBUM_KEYS[..],
+            &ARTWORK_KEYS[..],
+        ]
+        .iter()
+        .any(|keys| self.find(keys).is_some())
+        .then_some(self)
+    }
+
+    fn find(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .find_map(|key| self.fields.get(*key))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Track artist.
+    pub fn artist(&self) -> Option<&str> {
+        self.find(&ARTIST_KEYS)
+    }
+
+    /// Track title.
+    pub fn title(&self) -> Option<&str> {
+        self.find(&TITLE_KEYS)
+    }
+
+    /// Track album.
+    pub fn album(&self) -> Option<&str> {
+        self.find(&ALBUM_KEYS)
+    }
+
+    /// Artwork URL, taken from fields such as `artwork`, `image`, or `cover`.
+    pub fn artwork_url(&self) -> Option<&str> {
+        self.find(&ARTWORK_KEYS)
+    }
+
+    /// All fields. Keys are converted to lowercase.
+    pub fn fields(&self) -> &BTreeMap<String, String> {
+        &self.fields
+    }
+}
+
+/// `StreamUrl` value stored along with its classification so it only needs to be parsed once.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "String", into = "String"))]
+pub(crate) struct StreamUrl {
+    value: String,
+    kind: StreamUrlKind,
+}
+
+impl StreamUrl {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    pub(crate) fn kind(&self) -> &StreamUrlKind {
+        &self.kind
+    }
+}
+
+impl From<String> for StreamUrl {
+    fn from(value: String) -> Self {
+        let kind = StreamUrlKind::parse(&value);
+        Self { value, kind }
+    }
+}
+
+impl From<StreamUrl> for String {
+    fn from(stream_url: StreamUrl) -> Self {
+        stream_url.value
+    }
+}
+
+impl Debug for StreamUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
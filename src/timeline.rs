@@ -0,0 +1,173 @@
+//! Timed metadata for recorded streams.
+//!
+//! A [`MetadataTimeline`] records when each metadata value was received so it can be exported as
+//! track markers for a recording. `WebVTT` chapters and LRC files are supported, along with a
+//! JSON Lines format that can be imported again when the `serde` feature is enabled.
+
+#[cfg(feature = "serde")]
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use crate::IcyMetadata;
+#[cfg(feature = "serde")]
+use crate::error::InvalidTimelineError;
+
+/// Metadata value along with the time it was received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimelineEntry {
+    time: Duration,
+    metadata: IcyMetadata,
+}
+
+impl TimelineEntry {
+    /// Time the metadata was received, relative to the start of the recording.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Metadata value.
+    pub fn metadata(&self) -> &IcyMetadata {
+        &self.metadata
+    }
+
+    /// Text shown for the entry. Uses the `StreamTitle` if it's set, otherwise the artist and
+    /// title. Line breaks are replaced with spaces since both output formats are line-based.
+    fn label(&self) -> String {
+        let label = if let Some(stream_title) = self.metadata.stream_title() {
+            stream_title.to_string()
+        } else {
+            match self.metadata.artist_and_title() {
+                (Some(artist), Some(title)) => format!("{artist} - {title}"),
+                (artist, title) => title.or(artist).unwrap_or_default().to_string(),
+            }
+        };
+        label.replace(['\r', '\n'], " ")
+    }
+}
+
+/// Metadata values ordered by the time they were received.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetadataTimeline {
+    entries: Vec<TimelineEntry>,
+}
+
+/// Single line of the JSON Lines format.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JsonLine {
+    /// Time in seconds.
+    time: f64,
+    metadata: IcyMetadata,
+}
+
+impl MetadataTimeline {
+    /// Creates an empty `MetadataTimeline`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a metadata value received at `time`. Entries are kept in time order.
+    pub fn push(&mut self, time: Duration, metadata: IcyMetadata) {
+        let index = self.entries.partition_point(|entry| entry.time <= time);
+        self.entries.insert(index, TimelineEntry { time, metadata });
+    }
+
+    /// All entries in the timeline.
+    pub fn entries(&self) -> &[TimelineEntry] {
+        &self.entries
+    }
+
+    /// Writes the timeline as JSON Lines. Each line contains an object with the `time` in seconds
+    /// and the `metadata`.
+    #[cfg(feature = "serde")]
+    pub fn write_json_lines<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for entry in &self.entries {
+            let line = JsonLine {
+                time: entry.time.as_secs_f64(),
+                metadata: entry.metadata.clone(),
+            };
+            serde_json::to_writer(&mut writer, &line)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a timeline written by [`Self::write_json_lines`]. Blank lines are ignored.
+    #[cfg(feature = "serde")]
+    pub fn read_json_lines<R>(reader: R) -> Result<Self, InvalidTimelineError>
+    where
+        R: BufRead,
+    {
+        let mut timeline = Self::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| InvalidTimelineError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let JsonLine { time, metadata } = serde_json::from_str(&line)
+                .map_err(|e| InvalidTimelineError(format!("line {}: {e}", i + 1)))?;
+            let time = Duration::try_from_secs_f64(time)
+                .map_err(|e| InvalidTimelineError(format!("line {}: {e}", i + 1)))?;
+            timeline.push(time, metadata);
+        }
+        Ok(timeline)
+    }
+
+    /// Formats the timeline as `WebVTT` chapter cues. Each cue lasts until the next entry, and the
+    /// last cue lasts until `end`, which should be the length of the recording.
+    pub fn to_webvtt(&self, end: Duration) -> String {
+        let mut vtt = "WEBVTT\n".to_string();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let cue_end = self
+                .entries
+                .get(i + 1)
+                .map_or(end, |next| next.time)
+                .max(entry.time);
+            // Cue text can't contain the timing separator and uses HTML-style escaping
+            let label = entry
+                .label()
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            vtt.push_str(&format!(
+                "\n{}\n{} --> {}\n{label}\n",
+                i + 1,
+                vtt_timestamp(entry.time),
+                vtt_timestamp(cue_end)
+            ));
+        }
+        vtt
+    }
+
+    /// Formats the timeline as an LRC file with one line per entry.
+    pub fn to_lrc(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let centis = entry.time.as_millis() / 10;
+                format!(
+                    "[{:02}:{:02}.{:02}]{}\n",
+                    centis / 6000,
+                    centis / 100 % 60,
+                    centis % 100,
+                    entry.label()
+                )
+            })
+            .collect()
+    }
+}
+
+/// Formats a duration as `HH:MM:SS.mmm`.
+fn vtt_timestamp(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
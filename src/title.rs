@@ -0,0 +1,252 @@
+//! Splitting of stream titles into an artist and title.
+//!
+//! Most stations only send a `StreamTitle` that combines the artist and title, usually in the form
+//! `Artist - Title`. [`TitleParser`] splits these titles using a configurable list of separators
+//! and can optionally recognize the `Title by Artist` form. Since station formats vary, parsers can
+//! be overridden for specific stations.
+//!
+//! ```
+//! use icy_metadata::IcyMetadata;
+//! use icy_metadata::title::{TitleOrder, TitleParser};
+//!
+//! let parser = TitleParser::new().station_override(
+//!     "Backwards FM",
+//!     TitleParser::new().order(TitleOrder::TitleArtist),
+//! );
+//! let metadata = IcyMetadata::default().with_stream_title("Title / Artist feat. Guest");
+//! let parsed = metadata.parse_title(parser.for_station(Some("Backwards FM")));
+//! assert_eq!(parsed.artist(), Some("Artist feat. Guest"));
+//! assert_eq!(parsed.title(), Some("Title"));
+//! assert_eq!(parsed.featured(), ["Guest"]);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+pub(crate) static DEFAULT_PARSER: LazyLock<TitleParser> = LazyLock::new(TitleParser::new);
+
+// Markers that introduce featured artists. They must be preceded by a space or an opening bracket.
+const FEATURED_MARKERS: [&str; 5] = ["featuring ", "feat. ", "feat ", "ft. ", "ft "];
+
+/// Order of the artist and title on either side of a separator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TitleOrder {
+    /// `Artist - Title`
+    #[default]
+    ArtistTitle,
+    /// `Title - Artist`
+    TitleArtist,
+}
+
+/// Result of splitting a stream title.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParsedTitle<'a> {
+    artist: Option<&'a str>,
+    title: Option<&'a str>,
+    featured: Vec<&'a str>,
+    ambiguous: bool,
+}
+
+impl<'a> ParsedTitle<'a> {
+    pub(crate) fn new(artist: Option<&'a str>, title: Option<&'a str>) -> Self {
+        Self {
+            artist,
+            title,
+            ..Default::default()
+        }
+    }
+
+    /// Artist as written in the stream title, including any featured artists.
+    pub fn artist(&self) -> Option<&'a str> {
+        self.artist
+    }
+
+    /// Track title.
+    pub fn title(&self) -> Option<&'a str> {
+        self.title
+    }
+
+    /// Featured artists found in the artist or title, ex: `Guest` for `Artist feat. Guest`.
+    pub fn featured(&self) -> &[&'a str] {
+        &self.featured
+    }
+
+    /// Whether the stream title could have been split in more than one way, ex: `A - B - C` or
+    /// `A - B / C`. The preferred separator is used in this case. Titles split on "by" are always
+    /// ambiguous since it could be part of the title.
+    pub fn is_ambiguous(&self) -> bool {
+        self.ambiguous
+    }
+}
+
+/// Splits stream titles into an artist and title.
+///
+/// By default, titles are split on ` - `, ` – `, ` / `, and `|`, in that order of preference.
+/// Separators other than `|` require surrounding spaces so hyphenated names such as `Jay-Z` or
+/// `AC/DC` aren't split. Titles that can't be split are treated as a title with no artist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TitleParser {
+    separators: Vec<String>,
+    order: TitleOrder,
+    parse_by: bool,
+    stations: HashMap<String, Self>,
+}
+
+impl Default for TitleParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TitleParser {
+    /// Creates a new `TitleParser` with the default settings.
+    pub fn new() -> Self {
+        Self {
+            separators: [" - ", " \u{2013} ", " / ", "|"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            order: TitleOrder::default(),
+            parse_by: false,
+            stations: HashMap::new(),
+        }
+    }
+
+    /// Set the separators to split on, in order of preference. Empty separators are ignored.
+    pub fn separators<I, S>(mut self, separators: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.separators = separators
+            .into_iter()
+            .map(Into::into)
+            .filter(|separator| !separator.is_empty())
+            .collect();
+        self
+    }
+
+    /// Set the order of the artist and title. Defaults to [`TitleOrder::ArtistTitle`].
+    pub fn order(mut self, order: TitleOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Whether to recognize titles in the form `Title by Artist` if no separator is found. This is
+    /// disabled by default since many song titles contain "by", ex: `Stand by Me`. Titles split
+    /// this way are always marked as [ambiguous](ParsedTitle::is_ambiguous).
+    pub fn parse_by(mut self, parse_by: bool) -> Self {
+        self.parse_by = parse_by;
+        self
+    }
+
+    /// Uses `parser` for the station with the given name, as sent in the `icy-name` header.
+    pub fn station_override<S>(mut self, station: S, parser: Self) -> Self
+    where
+        S: Into<String>,
+    {
+        self.stations.insert(station.into(), parser);
+        self
+    }
+
+    /// Returns the parser to use for the given station, falling back to `self` if there's no
+    /// override.
+    pub fn for_station(&self, station: Option<&str>) -> &Self {
+        station
+            .and_then(|station| self.stations.get(station))
+            .unwrap_or(self)
+    }
+
+    /// Splits `stream_title` into an artist and title.
+    pub fn parse<'a>(&self, stream_title: &'a str) -> ParsedTitle<'a> {
+        let mut parsed = if let Some((separator, index)) = self.find_separator(stream_title) {
+            let first = non_empty(&stream_title[..index]);
+            let second = non_empty(&stream_title[index + separator.len()..]);
+            let (artist, title) = match self.order {
+                TitleOrder::ArtistTitle => (first, second),
+                TitleOrder::TitleArtist => (second, first),
+            };
+            ParsedTitle {
+                ambiguous: self.separator_count(stream_title) > 1,
+                ..ParsedTitle::new(artist, title)
+            }
+        } else if let Some(index) = stream_title.rfind(" by ").filter(|_| self.parse_by) {
+            ParsedTitle {
+                ambiguous: true,
+                ..ParsedTitle::new(
+                    non_empty(&stream_title[index + 4..]),
+                    non_empty(&stream_title[..index]),
+                )
+            }
+        } else {
+            ParsedTitle::new(None, non_empty(stream_title))
+        };
+        parsed.featured = [parsed.artist, parsed.title]
+            .into_iter()
+            .flatten()
+            .flat_map(featured_artists)
+            .collect();
+        parsed
+    }
+
+    fn find_separator<'s>(&'s self, stream_title: &str) -> Option<(&'s str, usize)> {
+        self.separators.iter().find_map(|separator| {
+            stream_title
+                .find(separator.as_str())
+                .map(|index| (separator.as_str(), index))
+        })
+    }
+
+    /// Counts the places `stream_title` could be split. Overlapping separators, ex: ` - ` and `-`,
+    /// are counted once.
+    fn separator_count(&self, stream_title: &str) -> usize {
+        let mut matches: Vec<_> = self
+            .separators
+            .iter()
+            .flat_map(|separator| {
+                stream_title
+                    .match_indices(separator.as_str())
+                    .map(|(index, found)| (index, index + found.len()))
+            })
+            .collect();
+        matches.sort_unstable();
+        let mut count = 0;
+        let mut end = 0;
+        for (start, match_end) in matches {
+            if count == 0 || start >= end {
+                count += 1;
+                end = match_end;
+            } else {
+                end = end.max(match_end);
+            }
+        }
+        count
+    }
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|value| !value.is_empty())
+}
+
+/// Finds the artists after a featuring marker, ex: `Artist feat. A & B` or `Title (ft. A, B)`.
+fn featured_artists(value: &str) -> Vec<&str> {
+    // ASCII lowercasing keeps the byte offsets the same
+    let lower = value.to_ascii_lowercase();
+    let found = FEATURED_MARKERS.iter().find_map(|marker| {
+        lower.match_indices(marker).find_map(|(index, _)| {
+            let before = lower[..index].chars().next_back()?;
+            matches!(before, ' ' | '(' | '[').then_some((index + marker.len(), before))
+        })
+    });
+    let Some((start, before)) = found else {
+        return Vec::new();
+    };
+    let rest = &value[start..];
+    let rest = match before {
+        '(' => rest.split(')').next().unwrap_or(rest),
+        '[' => rest.split(']').next().unwrap_or(rest),
+        // Stop at any bracketed suffix, ex: `Artist feat. Guest (Live)`
+        _ => rest.split(['(', '[']).next().unwrap_or(rest),
+    };
+    rest.split([',', '&']).filter_map(non_empty).collect()
+}
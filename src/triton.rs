@@ -0,0 +1,178 @@
+//! Parsing for the extended `StreamTitle` format used by Triton Digital and iHeart streams.
+//!
+//! These stations send the track information as a list of attributes within the `StreamTitle`,
+//! usually prefixed with the artist:
+//!
+//! ```text
+//! Artist - text="Title" song_spot="M" MediaBaseId="123" amgArtworkURL="http://..." length="00:03:25"
+//! ```
+//!
+//! When a `StreamTitle` in this format is parsed, the artist, title, and artwork URL are set on
+//! the [`IcyMetadata`](crate::IcyMetadata) and the `StreamTitle` is replaced with
+//! `Artist - Title`. The original `StreamTitle` and the full set of attributes are available from
+//! [`IcyMetadata::triton`](crate::IcyMetadata::triton).
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Type of content described by the `song_spot` attribute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpotType {
+    /// A song. Sent as `M` or `T`.
+    Music,
+    /// Spoken content or an ad. Sent as `F`.
+    Spot,
+    /// Any other value.
+    Other(String),
+}
+
+impl SpotType {
+    fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "M" | "T" => Self::Music,
+            "F" => Self::Spot,
+            _ => Self::Other(value.to_string()),
+        }
+    }
+
+    /// Whether the content is a song.
+    pub fn is_music(&self) -> bool {
+        *self == Self::Music
+    }
+}
+
+/// Structured fields parsed from a Triton-style `StreamTitle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TritonTitle {
+    raw: String,
+    artist: Option<String>,
+    attributes: HashMap<String, String>,
+}
+
+impl TritonTitle {
+    /// Parses a `StreamTitle` value. Returns `None` if it doesn't contain a `text` or `song_spot`
+    /// attribute.
+    pub fn parse(stream_title: &str) -> Option<Self> {
+        let start = attribute_start(stream_title)?;
+        let mut attributes = HashMap::new();
+        let mut rest = &stream_title[start..];
+        while let Some((key, value, remaining)) = next_attribute(rest) {
+            attributes.insert(key.to_string(), value.to_string());
+            rest = remaining;
+        }
+        let parsed = Self {
+            raw: stream_title.to_string(),
+            artist: Some(
+                stream_title[..start]
+                    .trim()
+                    .trim_end_matches(['-', '\u{2013}'])
+                    .trim_end(),
+            )
+            .filter(|artist| !artist.is_empty())
+            .map(str::to_string),
+            attributes,
+        };
+        (parsed.attribute("text").is_some() || parsed.attribute("song_spot").is_some())
+            .then_some(parsed)
+    }
+
+    /// The `StreamTitle` value as it was sent, before it was replaced with `Artist - Title`.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Track artist. This is taken from the text before the attributes, or the `artist` attribute
+    /// if there isn't any.
+    pub fn artist(&self) -> Option<&str> {
+        self.artist
+            .as_deref()
+            .or_else(|| self.non_empty_attribute("artist"))
+    }
+
+    /// Track title from the `text` attribute, or the `title` attribute if there isn't one.
+    pub fn title(&self) -> Option<&str> {
+        self.non_empty_attribute("text")
+            .or_else(|| self.non_empty_attribute("title"))
+    }
+
+    /// Type of content from the `song_spot` attribute.
+    pub fn spot_type(&self) -> Option<SpotType> {
+        self.non_empty_attribute("song_spot").map(SpotType::parse)
+    }
+
+    /// Artwork URL from the `amgArtworkURL` attribute.
+    pub fn artwork_url(&self) -> Option<&str> {
+        self.non_empty_attribute("amgArtworkURL")
+            .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+    }
+
+    /// Track length from the `length` attribute, formatted as `HH:MM:SS` or `MM:SS`.
+    pub fn duration(&self) -> Option<Duration> {
+        let length = self.non_empty_attribute("length")?;
+        let mut seconds = 0u64;
+        for part in length.split(':') {
+            seconds = seconds
+                .checked_mul(60)?
+                .checked_add(part.trim().parse().ok()?)?;
+        }
+        Some(Duration::from_secs(seconds))
+    }
+
+    /// All attributes, with their original names.
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        &self.attributes
+    }
+
+    /// Looks up an attribute, ignoring the case of the name.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn non_empty_attribute(&self, name: &str) -> Option<&str> {
+        self.attribute(name)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Whether `s` starts with an attribute name followed by `="`.
+fn is_attribute(s: &str) -> bool {
+    let name_len = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(s.len());
+    name_len > 0 && s[name_len..].starts_with("=\"")
+}
+
+/// Byte offset of the first attribute in `s`.
+fn attribute_start(s: &str) -> Option<usize> {
+    s.char_indices()
+        .filter(|(i, _)| *i == 0 || s[..*i].ends_with(char::is_whitespace))
+        .map(|(i, _)| i)
+        .find(|i| is_attribute(&s[*i..]))
+}
+
+/// Parses the attribute at the start of `s`, returning its name, value, and the remaining input.
+fn next_attribute(s: &str) -> Option<(&str, &str, &str)> {
+    let s = s.trim_start();
+    if !is_attribute(s) {
+        return None;
+    }
+    let (name, rest) = s.split_once("=\"")?;
+    // Values aren't escaped, so a quote only ends the value if it's followed by another attribute
+    // or the end of the input
+    let end = rest
+        .match_indices('"')
+        .map(|(i, _)| i)
+        .find(|i| {
+            let after = &rest[i + 1..];
+            let trimmed = after.trim_start();
+            trimmed.is_empty() || (trimmed.len() < after.len() && is_attribute(trimmed))
+        })
+        .or_else(|| rest.rfind('"'))?;
+    Some((name, &rest[..end], &rest[end + 1..]))
+}
@@ -0,0 +1,214 @@
+//! Demuxer for the Ultravox 2.1 protocol used by Shoutcast v2 servers.
+//!
+//! Ultravox streams are made up of framed messages rather than raw audio with interleaved
+//! metadata. Each message has a class and type that specify what the payload contains:
+//!
+//! - `0x7000` and `0x8000`-`0x8003`: MP3 and AAC audio data.
+//! - `0x3901` and `0x3902`: XML metadata, possibly split over multiple messages.
+//! - `0x4000`-`0x4003`: station artwork.
+//! - `0x5000`-`0x5003`: artwork for the currently playing track.
+//!
+//! [`UltravoxReader`] strips the framing so the audio can be passed to a decoder and sends any
+//! metadata it finds to a callback.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{self, Read};
+
+use tracing::warn;
+
+use crate::error::MetadataParseError;
+use crate::{Artwork, ArtworkKind, IcyMetadata};
+
+const SYNC_BYTE: u8 = 0x5A;
+// sync + reserved/qos + class/type (2) + payload length (2)
+const HEADER_LEN: usize = 6;
+// metadata id (2) + span (2) + index (2)
+const METADATA_HEADER_LEN: usize = 6;
+
+/// Reads audio and metadata from an Ultravox stream.
+pub struct UltravoxReader<T> {
+    inner: T,
+    audio: Vec<u8>,
+    audio_pos: usize,
+    assemblies: HashMap<u16, MetadataAssembly>,
+    on_metadata_read: Box<dyn Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync>,
+}
+
+impl<T> Debug for UltravoxReader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UltravoxReader")
+            .field("inner", &"<inner>")
+            .field("audio", &self.audio.len())
+            .field("audio_pos", &self.audio_pos)
+            .field("assemblies", &self.assemblies)
+            .field("on_metadata_read", &"<on_metadata_read>")
+            .finish()
+    }
+}
+
+impl<T> UltravoxReader<T> {
+    /// Creates a new `UltravoxReader`. `on_metadata_read` is called whenever a complete metadata
+    /// or artwork message is received.
+    pub fn new<F>(inner: T, on_metadata_read: F) -> Self
+    where
+        F: Fn(Result<IcyMetadata, MetadataParseError>) + Send + Sync + 'static,
+    {
+        Self {
+            inner,
+            audio: Vec::new(),
+            audio_pos: 0,
+            assemblies: HashMap::new(),
+            on_metadata_read: Box::new(on_metadata_read),
+        }
+    }
+}
+
+impl<T> UltravoxReader<T>
+where
+    T: Read,
+{
+    /// Reads the next message, returning `false` if the stream ended cleanly.
+    fn read_message(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; HEADER_LEN];
+        if !self.read_sync(&mut header[0])? {
+            return Ok(false);
+        }
+        self.inner.read_exact(&mut header[1..])?;
+        let class_type = u16::from_be_bytes([header[2], header[3]]);
+        let payload_len = u16::from_be_bytes([header[4], header[5]]) as usize;
+
+        let mut payload = vec![0u8; payload_len];
+        self.inner.read_exact(&mut payload)?;
+        let mut terminator = [0u8; 1];
+        self.inner.read_exact(&mut terminator)?;
+        if terminator[0] != 0 {
+            warn!(class_type, "ultravox message missing terminator");
+        }
+
+        match class_type >> 12 {
+            0x7 | 0x8 => {
+                self.audio = payload;
+                self.audio_pos = 0;
+            }
+            0x3 if matches!(class_type, 0x3901 | 0x3902) => {
+                if let Some(xml) = self.assemble(class_type, &payload) {
+                    let metadata = String::from_utf8(xml)
+                        .map_err(MetadataParseError::InvalidUtf8)
+                        .and_then(|xml| IcyMetadata::from_xml(&xml));
+                    (self.on_metadata_read)(metadata);
+                }
+            }
+            class @ (0x4 | 0x5) => {
+                if let Some(data) = self.assemble(class_type, &payload) {
+                    // An empty image means the previous artwork should be cleared
+                    if !data.is_empty() {
+                        let kind = if class == 0x4 {
+                            ArtworkKind::Station
+                        } else {
+                            ArtworkKind::Track
+                        };
+                        let artwork = Artwork::new(kind, image_mime_type(class_type), data);
+                        (self.on_metadata_read)(Ok(IcyMetadata::default().with_artwork(artwork)));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Finds the next sync byte, returning `false` if the stream ended.
+    fn read_sync(&mut self, sync: &mut u8) -> io::Result<bool> {
+        let mut skipped = 0;
+        loop {
+            if self.inner.read(std::slice::from_mut(sync))? == 0 {
+                return Ok(false);
+            }
+            if *sync == SYNC_BYTE {
+                if skipped > 0 {
+                    warn!(skipped, "lost sync with ultravox stream");
+                }
+                return Ok(true);
+            }
+            skipped += 1;
+        }
+    }
+
+    /// Adds a metadata message to the in-progress assembly, returning the full payload once all
+    /// parts have been received.
+    fn assemble(&mut self, class_type: u16, payload: &[u8]) -> Option<Vec<u8>> {
+        if payload.len() < METADATA_HEADER_LEN {
+            warn!(class_type, "ultravox metadata message too short");
+            return None;
+        }
+        let id = u16::from_be_bytes([payload[0], payload[1]]);
+        let span = u16::from_be_bytes([payload[2], payload[3]]).max(1) as usize;
+        // Indices are 1-based
+        let index = (u16::from_be_bytes([payload[4], payload[5]]) as usize).max(1) - 1;
+        let data = &payload[METADATA_HEADER_LEN..];
+
+        let assembly = self
+            .assemblies
+            .entry(class_type)
+            .or_insert_with(|| MetadataAssembly::new(id, span));
+        // A new ID means the previous metadata was abandoned
+        if assembly.id != id || assembly.parts.len() != span {
+            *assembly = MetadataAssembly::new(id, span);
+        }
+        if let Some(part) = assembly.parts.get_mut(index) {
+            *part = Some(data.to_vec());
+        }
+        if assembly.parts.iter().all(Option::is_some) {
+            let assembly = self.assemblies.remove(&class_type)?;
+            return Some(assembly.parts.into_iter().flatten().flatten().collect());
+        }
+        None
+    }
+}
+
+impl<T> Read for UltravoxReader<T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.audio_pos == self.audio.len() {
+            if !self.read_message()? {
+                return Ok(0);
+            }
+        }
+        let available = &self.audio[self.audio_pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.audio_pos += len;
+        Ok(len)
+    }
+}
+
+#[derive(Debug)]
+struct MetadataAssembly {
+    id: u16,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl MetadataAssembly {
+    fn new(id: u16, span: usize) -> Self {
+        Self {
+            id,
+            parts: vec![None; span],
+        }
+    }
+}
+
+fn image_mime_type(class_type: u16) -> &'static str {
+    match class_type & 0x0FFF {
+        0x000 => "image/jpeg",
+        0x001 => "image/png",
+        0x002 => "image/bmp",
+        0x003 => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
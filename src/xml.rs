@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+use crate::error::InvalidXmlError;
+
+/// Minimal owned XML element.
+///
+/// The documents we deal with are small and loosely structured, so it's simpler to build a tree
+/// and query it than to drive the event reader directly. Element and attribute names are matched
+/// case-insensitively since some formats (ASX in particular) don't specify a case.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Self>,
+    pub(crate) text: String,
+}
+
+impl Element {
+    /// Parses the first root element in `xml`.
+    pub(crate) fn parse(xml: &str) -> Result<Self, InvalidXmlError> {
+        let mut reader = Reader::from_str(xml.trim_start_matches('\u{feff}'));
+        // Hand-written playlists frequently have mismatched tag casing
+        reader.config_mut().check_end_names = false;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf).map_err(to_error)? {
+                Event::Start(start) => {
+                    let start = start.into_owned();
+                    return read_element(&mut reader, &start, &mut buf);
+                }
+                Event::Empty(start) => return element_from_start(&start),
+                Event::Eof => return Err(InvalidXmlError("no root element found".to_string())),
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Self> {
+        self.children
+            .iter()
+            .find(|child| child.name.eq_ignore_ascii_case(name))
+    }
+
+    pub(crate) fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> {
+        self.children
+            .iter()
+            .filter(move |child| child.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Text of the first child named `name`, if it exists and isn't blank.
+    pub(crate) fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name)
+            .map(|child| child.text.trim())
+            .filter(|text| !text.is_empty())
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn to_error(e: impl ToString) -> InvalidXmlError {
+    InvalidXmlError(e.to_string())
+}
+
+fn element_from_start(start: &BytesStart) -> Result<Element, InvalidXmlError> {
+    let mut attributes = Vec::new();
+    for attribute in start.attributes().with_checks(false) {
+        let attribute = attribute.map_err(to_error)?;
+        let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+        // ASX files commonly contain URLs with unescaped ampersands
+        let value = attribute.unescape_value().map_or_else(
+            |_| String::from_utf8_lossy(&attribute.value).into_owned(),
+            Cow::into_owned,
+        );
+        attributes.push((key, value));
+    }
+    Ok(Element {
+        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        attributes,
+        children: Vec::new(),
+        text: String::new(),
+    })
+}
+
+/// Reads the remainder of the element opened by `start`, including all of its children.
+pub(crate) fn read_element<R>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+    buf: &mut Vec<u8>,
+) -> Result<Element, InvalidXmlError>
+where
+    R: BufRead,
+{
+    let mut stack = vec![element_from_start(start)?];
+    loop {
+        buf.clear();
+        match reader.read_event_into(buf).map_err(to_error)? {
+            Event::Start(start) => stack.push(element_from_start(&start)?),
+            Event::Empty(start) => {
+                let element = element_from_start(&start)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+            }
+            Event::Text(text) => {
+                if let Some(current) = stack.last_mut() {
+                    // Some servers send invalid entities, fall back to the raw text in that case
+                    match text.unescape() {
+                        Ok(text) => current.text.push_str(&text),
+                        Err(_) => current.text.push_str(&String::from_utf8_lossy(&text)),
+                    }
+                }
+            }
+            Event::CData(data) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&String::from_utf8_lossy(&data));
+                }
+            }
+            Event::End(_) => {
+                let Some(element) = stack.pop() else {
+                    return Err(InvalidXmlError("unbalanced end tag".to_string()));
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Eof => return Err(InvalidXmlError("unexpected end of document".to_string())),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::IcyMetadata;
+use crate::error::{EmptyMetadataError, MetadataParseError};
+use crate::xml::Element;
+
+impl IcyMetadata {
+    /// Parses metadata sent in the XML format used by Shoutcast v2 sources and Ultravox streams.
+    ///
+    /// ```xml
+    /// <metadata>
+    ///   <TIT2>Title</TIT2>
+    ///   <TPE1>Artist</TPE1>
+    ///   <TALB>Album</TALB>
+    ///   <extension>
+    ///     <title seq="1">Artist - Title</title>
+    ///     <title seq="2">Next Artist - Next Title</title>
+    ///   </extension>
+    /// </metadata>
+    /// ```
+    ///
+    /// The ID3-style elements map to [`title`](Self::title), [`artist`](Self::artist), and
+    /// [`album`](Self::album). Artwork references are read from `APIC` elements and from
+    /// `albumart` or `stationart` elements within the extension block. Any other elements are
+    /// stored in [`custom_fields`](Self::custom_fields) using the element name as the key.
+    ///
+    /// [`stream_title`](Self::stream_title) is filled in using the first extension title, or by
+    /// combining the artist and title if there are no extension titles. If `xml` isn't an XML
+    /// document, it's parsed as standard `StreamTitle='...';` metadata instead.
+    pub fn from_xml(xml: &str) -> Result<Self, MetadataParseError> {
+        let xml = xml.trim_start_matches('\u{feff}').trim();
+        if !xml.starts_with('<') {
+            return xml.parse().map_err(MetadataParseError::Empty);
+        }
+
+        let root = Element::parse(xml).map_err(MetadataParseError::InvalidXml)?;
+        let mut metadata = Self::default();
+        let mut extension_titles = Vec::new();
+        for child in &root.children {
+            let text = child.text.trim();
+            match child.name.to_ascii_lowercase().as_str() {
+                "tit2" => metadata.title = non_empty(text),
+                "tpe1" => metadata.artist = non_empty(text),
+                "talb" => metadata.album = non_empty(text),
+                "streamtitle" => metadata.stream_title = non_empty(text),
+                "streamurl" => metadata.stream_url = non_empty(text).map(Into::into),
+                "apic" => metadata.artwork_urls.extend(artwork_reference(child)),
+                "extension" => {
+                    for extension in &child.children {
+                        let text = extension.text.trim();
+                        match extension.name.to_ascii_lowercase().as_str() {
+                            "title" if !text.is_empty() => {
+                                let seq = extension
+                                    .attribute("seq")
+                                    .and_then(|seq| seq.trim().parse().ok())
+                                    .unwrap_or(u32::MAX);
+                                extension_titles.push((seq, text.to_string()));
+                            }
+                            "albumart" | "stationart" => {
+                                metadata.artwork_urls.extend(artwork_reference(extension));
+                            }
+                            _ if !text.is_empty() => {
+                                metadata
+                                    .custom
+                                    .insert(extension.name.clone(), text.to_string());
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ if !text.is_empty() => {
+                    metadata.custom.insert(child.name.clone(), text.to_string());
+                }
+                _ => {}
+            }
+        }
+        // Stable sort keeps titles without a sequence number in document order
+        extension_titles.sort_by_key(|(seq, _)| *seq);
+        metadata.extension_titles = extension_titles
+            .into_iter()
+            .map(|(_, title)| title)
+            .collect();
+
+        if metadata.stream_title.is_none() {
+            metadata.stream_title = metadata.extension_titles.first().cloned();
+        }
+        metadata.fill_stream_title();
+
+        if metadata == Self::default() {
+            return Err(MetadataParseError::Empty(EmptyMetadataError(
+                xml.to_string(),
+            )));
+        }
+        Ok(metadata)
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn artwork_reference(element: &Element) -> Option<String> {
+    element
+        .attribute("url")
+        .or_else(|| element.attribute("href"))
+        .map(str::trim)
+        .or_else(|| Some(element.text.trim()))
+        .and_then(non_empty)
+}
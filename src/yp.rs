@@ -0,0 +1,427 @@
+//! Support for Icecast's YP (yellow pages) stream directory.
+//!
+//! The directory publishes a dump of every listed station at `https://dir.xiph.org/yp.xml`.
+//! The dump is large, so [`YpReader`] parses it incrementally and yields one [`YpEntry`] at a
+//! time instead of loading the whole document.
+//!
+//! Stations are listed in the directory using [`YpClient`], which implements the `add`, `touch`
+//! and `remove` actions of the YP protocol.
+
+use std::fmt::Debug;
+use std::io::BufRead;
+#[cfg(feature = "reqwest")]
+use std::time::Duration;
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use crate::error::InvalidXmlError;
+#[cfg(feature = "reqwest")]
+use crate::error::YpError;
+use crate::xml::{Element, read_element};
+use crate::{IcyHeaders, IcyMetadata};
+
+/// Station listed in a YP directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct YpEntry {
+    server_name: Option<String>,
+    listen_url: Option<String>,
+    server_type: Option<String>,
+    bitrate: Option<u32>,
+    channels: Option<u16>,
+    sample_rate: Option<u32>,
+    genre: Vec<String>,
+    current_song: Option<String>,
+}
+
+impl YpEntry {
+    fn from_element(element: &Element) -> Self {
+        let field = |name| element.child_text(name);
+        let number = |name| field(name).and_then(|value| value.parse().ok());
+        Self {
+            server_name: field("server_name").map(str::to_string),
+            listen_url: field("listen_url").map(str::to_string),
+            server_type: field("server_type").map(str::to_string),
+            bitrate: number("bitrate"),
+            channels: field("channels").and_then(|value| value.parse().ok()),
+            sample_rate: number("samplerate"),
+            // The directory stores genres as a space-separated list
+            genre: field("genre")
+                .map(|genre| genre.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+            current_song: field("current_song").map(str::to_string),
+        }
+    }
+
+    /// Station name.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// URL that listeners connect to.
+    pub fn listen_url(&self) -> Option<&str> {
+        self.listen_url.as_deref()
+    }
+
+    /// Content type of the stream, ex: `audio/mpeg`.
+    pub fn server_type(&self) -> Option<&str> {
+        self.server_type.as_deref()
+    }
+
+    /// Stream bitrate in kbps.
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bitrate
+    }
+
+    /// Number of channels in the stream.
+    pub fn channels(&self) -> Option<u16> {
+        self.channels
+    }
+
+    /// Stream sample rate.
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    /// Station genres.
+    pub fn genre(&self) -> &[String] {
+        &self.genre
+    }
+
+    /// Song that was playing when the directory was generated.
+    pub fn current_song(&self) -> Option<&str> {
+        self.current_song.as_deref()
+    }
+}
+
+impl From<&YpEntry> for IcyHeaders {
+    fn from(entry: &YpEntry) -> Self {
+        let mut headers = Self::default().with_genre(&entry.genre);
+        if let Some(name) = &entry.server_name {
+            headers = headers.with_name(name);
+        }
+        if let Some(bitrate) = entry.bitrate {
+            headers = headers.with_bitrate(bitrate);
+        }
+        if let Some(sample_rate) = entry.sample_rate {
+            headers = headers.with_sample_rate(sample_rate);
+        }
+        if let Some(channels) = entry.channels {
+            headers = headers.with_channels(channels);
+        }
+        headers
+    }
+}
+
+impl From<&YpEntry> for IcyMetadata {
+    fn from(entry: &YpEntry) -> Self {
+        let mut metadata = Self::default();
+        metadata.stream_title.clone_from(&entry.current_song);
+        metadata
+    }
+}
+
+/// Iterator over the entries in a YP directory dump.
+///
+/// Only the current entry is kept in memory, so this can be used directly on a network response
+/// or a file. Iteration stops after the first error.
+pub struct YpReader<R> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R> Debug for YpReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("YpReader")
+            .field("reader", &"<reader>")
+            .field("buf", &self.buf.len())
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<R> YpReader<R>
+where
+    R: BufRead,
+{
+    /// Creates a new `YpReader` over a `yp.xml` document.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: Reader::from_reader(reader),
+            buf: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn next_entry(&mut self) -> Result<Option<YpEntry>, InvalidXmlError> {
+        loop {
+            self.buf.clear();
+            match self
+                .reader
+                .read_event_into(&mut self.buf)
+                .map_err(|e| InvalidXmlError(e.to_string()))?
+            {
+                Event::Start(start) if start.local_name().as_ref() == b"entry" => {
+                    let start = start.into_owned();
+                    let element = read_element(&mut self.reader, &start, &mut self.buf)?;
+                    return Ok(Some(YpEntry::from_element(&element)));
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R> Iterator for YpReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<YpEntry, InvalidXmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let entry = self.next_entry().transpose();
+        if !matches!(entry, Some(Ok(_))) {
+            self.done = true;
+        }
+        entry
+    }
+}
+
+/// Response returned after a successful YP request.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct YpResponse {
+    message: Option<String>,
+}
+
+#[cfg(feature = "reqwest")]
+impl YpResponse {
+    /// Message returned by the directory, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// Client for listing a stream in a YP directory.
+///
+/// The directory assigns a session ID (SID) when the stream is added, which is used to identify
+/// the stream in subsequent `touch` and `remove` requests. The directory also specifies how often
+/// it expects to be touched, which is available from [`YpClient::touch_frequency`]. Streams that
+/// aren't touched in time are removed from the listing.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug)]
+pub struct YpClient {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    listen_url: String,
+    content_type: String,
+    password: Option<String>,
+    sid: Option<String>,
+    touch_frequency: Option<Duration>,
+}
+
+#[cfg(feature = "reqwest")]
+impl YpClient {
+    /// Creates a new `YpClient` that lists the stream located at `listen_url` in the directory
+    /// at `url`, ex: `http://dir.xiph.org/cgi-bin/yp-cgi`.
+    /// Defaults to a content type of `audio/mpeg`.
+    pub fn new<S>(url: reqwest::Url, listen_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            listen_url: listen_url.into(),
+            content_type: "audio/mpeg".to_string(),
+            password: None,
+            sid: None,
+            touch_frequency: None,
+        }
+    }
+
+    /// Set the HTTP client used to send requests.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Set the content type of the stream.
+    pub fn content_type<S>(mut self, content_type: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// Set the password used to claim the listing. Most directories don't require one.
+    pub fn password<S>(mut self, password: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Session ID assigned by the directory. This is set after a successful
+    /// [`add`](Self::add) and cleared after [`remove`](Self::remove).
+    pub fn sid(&self) -> Option<&str> {
+        self.sid.as_deref()
+    }
+
+    /// How often the directory expects to receive a [`touch`](Self::touch).
+    pub fn touch_frequency(&self) -> Option<Duration> {
+        self.touch_frequency
+    }
+
+    /// Builds the form fields for the `add` action.
+    pub fn add_form(
+        &self,
+        headers: &IcyHeaders,
+        metadata: Option<&IcyMetadata>,
+    ) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("action", "add".to_string()),
+            ("sn", headers.name().unwrap_or_default().to_string()),
+            ("genre", headers.genre().join(" ")),
+            ("cpswd", self.password.clone().unwrap_or_default()),
+            (
+                "desc",
+                headers.description().unwrap_or_default().to_string(),
+            ),
+            ("url", headers.station_url().unwrap_or_default().to_string()),
+            ("listenurl", self.listen_url.clone()),
+            ("type", self.content_type.clone()),
+        ];
+        if let Some(bitrate) = headers.bitrate() {
+            fields.push(("b", bitrate.to_string()));
+        }
+        if let Some(channels) = headers.channels() {
+            fields.push(("channels", channels.to_string()));
+        }
+        if let Some(sample_rate) = headers.sample_rate() {
+            fields.push(("samplerate", sample_rate.to_string()));
+        }
+        if let Some(quality) = headers.quality() {
+            fields.push(("quality", quality));
+        }
+        if let Some(title) = metadata.and_then(IcyMetadata::stream_title) {
+            fields.push(("st", title.to_string()));
+        }
+        fields
+    }
+
+    /// Builds the form fields for the `touch` action.
+    pub fn touch_form(
+        &self,
+        metadata: &IcyMetadata,
+        listeners: u32,
+    ) -> Result<Vec<(&'static str, String)>, YpError> {
+        let sid = self.sid.clone().ok_or(YpError::NotListed)?;
+        Ok(vec![
+            ("action", "touch".to_string()),
+            ("sid", sid),
+            (
+                "st",
+                metadata.stream_title().unwrap_or_default().to_string(),
+            ),
+            ("listeners", listeners.to_string()),
+        ])
+    }
+
+    /// Adds the stream to the directory. The assigned SID and touch frequency are stored for
+    /// subsequent requests.
+    pub async fn add(
+        &mut self,
+        headers: &IcyHeaders,
+        metadata: Option<&IcyMetadata>,
+    ) -> Result<YpResponse, YpError> {
+        let (response_headers, response) = self.send(&self.add_form(headers, metadata)).await?;
+        let header = |name| {
+            response_headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+        let sid = header("sid")
+            .filter(|sid| !sid.is_empty())
+            .ok_or_else(|| YpError::Rejected("no SID returned".to_string()))?;
+        self.sid = Some(sid.to_string());
+        self.touch_frequency = header("touchfreq")
+            .and_then(|freq| freq.parse().ok())
+            .map(Duration::from_secs);
+        Ok(response)
+    }
+
+    /// Updates the current title and listener count. This also keeps the listing alive.
+    pub async fn touch(
+        &self,
+        metadata: &IcyMetadata,
+        listeners: u32,
+    ) -> Result<YpResponse, YpError> {
+        let form = self.touch_form(metadata, listeners)?;
+        let (_, response) = self.send(&form).await?;
+        Ok(response)
+    }
+
+    /// Removes the stream from the directory.
+    pub async fn remove(&mut self) -> Result<YpResponse, YpError> {
+        let sid = self.sid.clone().ok_or(YpError::NotListed)?;
+        let (_, response) = self
+            .send(&[("action", "remove".to_string()), ("sid", sid)])
+            .await?;
+        self.sid = None;
+        self.touch_frequency = None;
+        Ok(response)
+    }
+
+    async fn send(
+        &self,
+        form: &[(&'static str, String)],
+    ) -> Result<(http::HeaderMap, YpResponse), YpError> {
+        let body = form
+            .iter()
+            .map(|(key, value)| format!("{key}={}", urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(
+                http::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        if !status.is_success() {
+            return Err(YpError::UnexpectedStatus {
+                status: status.as_u16(),
+                body: response.text().await?,
+            });
+        }
+        // The result is reported through the YPResponse and YPMessage headers
+        let message = headers
+            .get("ypmessage")
+            .and_then(|value| value.to_str().ok())
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty());
+        let succeeded = headers
+            .get("ypresponse")
+            .is_some_and(|value| value.as_bytes().trim_ascii() == b"1");
+        if succeeded {
+            Ok((headers, YpResponse { message }))
+        } else {
+            Err(YpError::Rejected(message.unwrap_or_default()))
+        }
+    }
+}
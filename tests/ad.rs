@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use icy_metadata::IcyMetadata;
+use icy_metadata::ad::{AdEvent, AdRules, AdState};
+
+fn parse(metadata: &str) -> IcyMetadata {
+    metadata.parse().unwrap()
+}
+
+#[test]
+fn adswizz_break() {
+    let mut state = AdState::new(AdRules::new());
+    assert_eq!(state.update(&parse("StreamTitle='Artist - Title';")), None);
+    assert_eq!(
+        state.update(&parse(
+            "StreamTitle='';adw_ad='true';durationMilliseconds='30500';insertionType='midroll';"
+        )),
+        Some(AdEvent::AdBreakStarted {
+            expected_duration: Some(Duration::from_millis(30500)),
+            insertion_type: Some("midroll".to_string()),
+        })
+    );
+    assert!(state.in_ad_break());
+    // Later ads in the same break don't start a new one
+    assert_eq!(state.update(&parse("StreamTitle='';adw_ad='TRUE';")), None);
+    assert_eq!(
+        state.update(&parse("StreamTitle='Artist - Next';")),
+        Some(AdEvent::AdBreakEnded)
+    );
+    assert!(!state.in_ad_break());
+
+    assert_eq!(
+        state.update(&parse("StreamTitle='';adw_ad='true';insertionType='';")),
+        Some(AdEvent::AdBreakStarted {
+            expected_duration: None,
+            insertion_type: None,
+        })
+    );
+}
+
+#[test]
+fn triton_spot() {
+    let mut state = AdState::new(AdRules::new());
+    assert_eq!(
+        state.update(&parse(
+            r#"StreamTitle='Artist - text="Title" song_spot="M" length="00:03:25"';"#
+        )),
+        None
+    );
+    assert_eq!(
+        state.update(&parse(
+            r#"StreamTitle='text="Sponsor" song_spot="F" length="00:00:30"';"#
+        )),
+        Some(AdEvent::AdBreakStarted {
+            expected_duration: Some(Duration::from_secs(30)),
+            insertion_type: None,
+        })
+    );
+
+    let mut state = AdState::new(AdRules::new().triton(false));
+    assert_eq!(
+        state.update(&parse(r#"StreamTitle='text="Sponsor" song_spot="F"';"#)),
+        None
+    );
+}
+
+#[test]
+fn title_rules() {
+    let empty = IcyMetadata::default().with_stream_title(" ");
+    let station = IcyMetadata::default().with_stream_title("Station FM");
+    let mut state = AdState::new(AdRules::new());
+    assert_eq!(state.update(&empty), None);
+
+    let mut state = AdState::new(AdRules::new().empty_title(true));
+    assert!(matches!(
+        state.update(&empty),
+        Some(AdEvent::AdBreakStarted {
+            expected_duration: None,
+            insertion_type: None,
+        })
+    ));
+
+    let mut state =
+        AdState::new(AdRules::new().station_name_title(true)).station_name("station fm");
+    assert!(state.update(&station).is_some());
+    assert!(state.in_ad_break());
+}
+
+#[test]
+fn custom_field_rule() {
+    let mut state = AdState::new(AdRules::new().adswizz(false).custom_field("spot", "Yes"));
+    assert_eq!(state.update(&parse("StreamTitle='';adw_ad='true';")), None);
+    assert!(
+        state
+            .update(&parse("StreamTitle='Ad';spot='yes';"))
+            .is_some()
+    );
+}
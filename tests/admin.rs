@@ -0,0 +1,150 @@
+#![cfg(feature = "reqwest")]
+
+use icy_metadata::IcyMetadata;
+use icy_metadata::admin::{AdminClient, MetadataCharset, ServerKind};
+use icy_metadata::error::AdminError;
+use rstest::rstest;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+async fn serve_once(response: &'static str) -> (u16, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut request = Vec::new();
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            request.push(line);
+        }
+        stream.write_all(response.as_bytes()).await.unwrap();
+        request
+    });
+    (port, handle)
+}
+
+#[test]
+fn icecast_update_url() {
+    let client = AdminClient::new(
+        "http://localhost:8000".parse().unwrap(),
+        ServerKind::Icecast {
+            mount: "/live".to_string(),
+        },
+    );
+    let metadata = IcyMetadata::default().with_stream_title("Artist - Tïtle & more");
+    assert_eq!(
+        client.update_url(&metadata).as_str(),
+        "http://localhost:8000/admin/metadata?mount=%2Flive&mode=updinfo&song=Artist%20-%20T%C3%\
+         AFtle%20%26%20more&charset=UTF-8"
+    );
+
+    let client = client.charset(MetadataCharset::Latin1);
+    assert_eq!(
+        client.update_url(&metadata).as_str(),
+        "http://localhost:8000/admin/metadata?mount=%2Flive&mode=updinfo&song=Artist%20-%20T%EFtle%\
+         20%26%20more&charset=ISO-8859-1"
+    );
+}
+
+#[rstest]
+#[case("http://localhost:8000/radio")]
+#[case("http://localhost:8000/radio/")]
+fn update_url_keeps_base_path(#[case] base_url: &str) {
+    let metadata = IcyMetadata::default().with_stream_title("title");
+    let client = AdminClient::new(
+        base_url.parse().unwrap(),
+        ServerKind::Icecast {
+            mount: "/live".to_string(),
+        },
+    );
+    assert!(
+        client
+            .update_url(&metadata)
+            .as_str()
+            .starts_with("http://localhost:8000/radio/admin/metadata?")
+    );
+
+    let client = AdminClient::new(
+        base_url.parse().unwrap(),
+        ServerKind::Shoutcast { sid: None },
+    );
+    assert!(
+        client
+            .update_url(&metadata)
+            .as_str()
+            .starts_with("http://localhost:8000/radio/admin.cgi?")
+    );
+}
+
+#[test]
+fn shoutcast_update_url() {
+    let client = AdminClient::new(
+        "http://localhost:8000".parse().unwrap(),
+        ServerKind::Shoutcast { sid: Some(2) },
+    )
+    .credentials("admin", "pass");
+    let metadata = IcyMetadata::default()
+        .with_stream_title("title")
+        .with_stream_url("http://url");
+    assert_eq!(
+        client.update_url(&metadata).as_str(),
+        "http://localhost:8000/admin.cgi?pass=pass&mode=updinfo&song=title&sid=2&url=http%3A%2F%\
+         2Furl"
+    );
+}
+
+#[tokio::test]
+async fn icecast_update_success() {
+    let (port, server) = serve_once(
+        "HTTP/1.0 200 OK\r\nContent-Type: text/xml\r\n\r\n<?xml \
+         version=\"1.0\"?>\n<iceresponse><message>Metadata update \
+         successful</message><return>1</return></iceresponse>",
+    )
+    .await;
+
+    let response = AdminClient::new(
+        format!("http://127.0.0.1:{port}").parse().unwrap(),
+        ServerKind::Icecast {
+            mount: "/live".to_string(),
+        },
+    )
+    .credentials("admin", "hackme")
+    .update_metadata(&IcyMetadata::default().with_stream_title("title"))
+    .await
+    .unwrap();
+    assert_eq!(response.message(), Some("Metadata update successful"));
+
+    let request = server.await.unwrap();
+    assert!(request[0].starts_with("GET /admin/metadata?mount=%2Flive&mode=updinfo&song=title"));
+    assert!(
+        request
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("authorization: Basic YWRtaW46aGFja21l"))
+    );
+}
+
+#[tokio::test]
+async fn icecast_update_rejected() {
+    let (port, _server) = serve_once(
+        "HTTP/1.0 200 OK\r\nContent-Type: text/xml\r\n\r\n<?xml \
+         version=\"1.0\"?>\n<iceresponse><message>No such \
+         mountpoint</message><return>0</return></iceresponse>",
+    )
+    .await;
+
+    let res = AdminClient::new(
+        format!("http://127.0.0.1:{port}").parse().unwrap(),
+        ServerKind::Icecast {
+            mount: "/missing".to_string(),
+        },
+    )
+    .update_metadata(&IcyMetadata::default().with_stream_title("title"))
+    .await;
+    assert!(matches!(res, Err(AdminError::Rejected(message)) if message == "No such mountpoint"));
+}
@@ -0,0 +1,166 @@
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::{HeaderMap, HeaderValue, StatusCode};
+use icy_metadata::capture::{CaptureReplay, CaptureTap, CaptureWriter};
+use icy_metadata::{IcyHeaders, IcyMetadataReader};
+use rstest::rstest;
+
+// Header casing, order, and invalid lines should all be kept
+const ICY_HEAD: &[u8] =
+    b"ICY 200 OK\r\nicy-notice1:<BR>Notice<BR>\r\nICY-NAME:Station\r\nnot a header\r\nicy-metaint:4\r\n\r\n";
+const HTTP_HEAD: &[u8] = b"HTTP/1.0 200 OK\r\nicy-name: Station\r\nicy-metaint: 4\r\n\r\n";
+
+fn headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("icy-notice1", HeaderValue::from_static("<BR>Notice<BR>"));
+    headers.insert("icy-name", HeaderValue::from_static("Station"));
+    headers.insert("icy-metaint", HeaderValue::from_static("4"));
+    headers
+}
+
+fn body() -> Vec<u8> {
+    let mut body = b"abcd".to_vec();
+    let metadata = b"StreamTitle='Artist - Title';";
+    body.push(2);
+    body.extend_from_slice(metadata);
+    body.resize(body.len() + 32 - metadata.len(), 0);
+    body.extend_from_slice(b"efgh");
+    body.push(0);
+    body
+}
+
+#[test]
+fn capture_and_replay() {
+    let writer = CaptureWriter::new(Vec::new(), ICY_HEAD).unwrap();
+    // Small reads so the body is split into several chunks
+    let mut tap = CaptureTap::new(io::Cursor::new(body()), writer);
+    let mut passed_through = Vec::new();
+    let mut buf = [0; 7];
+    loop {
+        let read = tap.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        passed_through.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(passed_through, body());
+    let capture = tap.into_parts().1.into_inner();
+    assert!(capture.starts_with(&[b"ICYCAP1\n", ICY_HEAD].concat()));
+
+    let replay = CaptureReplay::new(capture.as_slice()).unwrap();
+    assert!(replay.is_icy());
+    assert_eq!(replay.status(), StatusCode::OK);
+    assert_eq!(replay.reason(), "OK");
+    assert_eq!(replay.headers(), &headers());
+    assert_eq!(replay.raw_head(), ICY_HEAD);
+    let icy_headers = IcyHeaders::parse_from_headers(replay.headers());
+    assert_eq!(icy_headers.name(), Some("Station"));
+
+    let titles = Arc::new(Mutex::new(Vec::new()));
+    let mut reader = IcyMetadataReader::new(replay, icy_headers.metadata_interval(), {
+        let titles = titles.clone();
+        move |metadata| {
+            let metadata = metadata.unwrap();
+            titles
+                .lock()
+                .unwrap()
+                .push(metadata.stream_title().map(str::to_string));
+        }
+    });
+    let mut audio = Vec::new();
+    reader.read_to_end(&mut audio).unwrap();
+    assert_eq!(audio, b"abcdefgh");
+    assert_eq!(
+        *titles.lock().unwrap(),
+        [Some("Artist - Title".to_string())]
+    );
+}
+
+#[test]
+fn realtime_replay() {
+    let mut writer = CaptureWriter::new(Vec::new(), HTTP_HEAD).unwrap();
+    writer.write_chunk(b"ab").unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    writer.write_chunk(b"cd").unwrap();
+    let capture = writer.into_inner();
+
+    let start = Instant::now();
+    let mut data = Vec::new();
+    CaptureReplay::new(capture.as_slice())
+        .unwrap()
+        .realtime(true)
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"abcd");
+    assert!(start.elapsed() >= Duration::from_millis(90));
+}
+
+#[rstest]
+#[case::backwards(&[50_000, 10_000])]
+#[case::too_far_ahead(&[0, 3_600_000_000])]
+fn invalid_realtime_timestamps(#[case] times: &[u64]) {
+    let mut capture = b"ICYCAP1\nHTTP/1.0 200 OK\r\n\r\n".to_vec();
+    for time in times {
+        capture.extend_from_slice(&time.to_be_bytes());
+        capture.extend_from_slice(&1u32.to_be_bytes());
+        capture.push(b'a');
+    }
+    let mut data = Vec::new();
+    // Without realtime, the timestamps are ignored
+    CaptureReplay::new(capture.as_slice())
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"aa");
+
+    let mut replay = CaptureReplay::new(capture.as_slice())
+        .unwrap()
+        .realtime(true);
+    assert_eq!(
+        replay.read_to_end(&mut data).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn invalid_capture() {
+    assert!(CaptureWriter::new(Vec::new(), b"ICY 200 OK\r\nX: y").is_err());
+    assert!(CaptureWriter::new(Vec::new(), b"ICY 200 OK\r\n\r\nbody").is_err());
+    assert!(CaptureWriter::new(Vec::new(), b"garbage\r\n\r\n").is_err());
+    assert!(CaptureReplay::new(b"HTTP/1.0 200 OK\r\n\r\n".as_slice()).is_err());
+
+    let mut writer = CaptureWriter::new(Vec::new(), ICY_HEAD).unwrap();
+    writer.write_chunk(b"abcd").unwrap();
+    let mut capture = writer.into_inner();
+    capture.truncate(capture.len() - 2);
+    let mut data = Vec::new();
+    let mut replay = CaptureReplay::new(capture.as_slice()).unwrap();
+    assert_eq!(
+        replay.read_to_end(&mut data).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_tap() {
+    let writer = CaptureWriter::new(Vec::new(), ICY_HEAD).unwrap();
+    let body = body();
+    let mut tap = CaptureTap::new(body.as_slice(), writer);
+    let mut data = Vec::new();
+    // The tap implements both `Read` and `AsyncRead`
+    tokio::io::AsyncReadExt::read_to_end(&mut tap, &mut data)
+        .await
+        .unwrap();
+    assert_eq!(data, body);
+
+    let capture = tap.into_parts().1.into_inner();
+    let mut replayed = Vec::new();
+    CaptureReplay::new(capture.as_slice())
+        .unwrap()
+        .read_to_end(&mut replayed)
+        .unwrap();
+    assert_eq!(replayed, body);
+}
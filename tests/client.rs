@@ -0,0 +1,142 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use icy_metadata::IcyMetadataReader;
+use icy_metadata::client::IcyClient;
+
+fn icy_body() -> Vec<u8> {
+    let metadata = b"StreamTitle='title';";
+    let mut body = vec![1; 4];
+    body.push(2);
+    body.extend_from_slice(metadata);
+    body.extend_from_slice(&vec![0; 32 - metadata.len()]);
+    body.extend_from_slice(&[1; 3]);
+    body
+}
+
+fn serve_once(response_head: &'static str) -> (u16, thread::JoinHandle<Vec<String>>) {
+    serve_once_on("127.0.0.1:0", response_head)
+}
+
+fn serve_once_on(
+    address: &str,
+    response_head: &'static str,
+) -> (u16, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind(address).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            request.push(line);
+        }
+        let mut stream = stream;
+        stream.write_all(response_head.as_bytes()).unwrap();
+        stream.write_all(&icy_body()).unwrap();
+        request
+    });
+    (port, handle)
+}
+
+#[test]
+fn icy_status_line() {
+    let (port, server) = serve_once(
+        "ICY 200 OK\r\nicy-notice1:<BR>This stream requires \
+         Winamp<BR>\r\nicy-name:name\r\nicy-metaint:4\r\n\r\n",
+    );
+
+    let response = IcyClient::from_uri(&format!("http://127.0.0.1:{port}/;").parse().unwrap())
+        .unwrap()
+        .connect()
+        .unwrap();
+    assert!(response.is_icy());
+    assert_eq!(response.status(), 200);
+
+    let icy_headers = response.icy_headers();
+    assert_eq!(icy_headers.name(), Some("name"));
+    assert_eq!(
+        icy_headers.notice1(),
+        Some("<BR>This stream requires Winamp<BR>")
+    );
+
+    let metadata = Arc::new(Mutex::new(Vec::new()));
+    let mut reader = {
+        let metadata = metadata.clone();
+        IcyMetadataReader::new(
+            response.into_inner(),
+            icy_headers.metadata_interval(),
+            move |meta| metadata.lock().unwrap().push(meta.unwrap()),
+        )
+    };
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, vec![1; 7]);
+    assert_eq!(metadata.lock().unwrap()[0].stream_title(), Some("title"));
+
+    let request = server.join().unwrap();
+    assert_eq!(request[0], "GET /; HTTP/1.0");
+    assert!(
+        request
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("icy-metadata: 1"))
+    );
+}
+
+#[test]
+fn http_status_line() {
+    let (port, _server) = serve_once("HTTP/1.0 200 OK\nicy-metaint: 4\n\n");
+
+    let response = IcyClient::new("127.0.0.1", port, "stream")
+        .connect()
+        .unwrap();
+    assert!(!response.is_icy());
+    assert_eq!(response.raw_head(), b"HTTP/1.0 200 OK\nicy-metaint: 4\n\n");
+    assert_eq!(response.icy_headers().metadata_interval().unwrap().get(), 4);
+}
+
+#[test]
+fn ipv6_uri() {
+    let (port, server) = serve_once_on("[::1]:0", "ICY 200 OK\r\nicy-metaint:4\r\n\r\n");
+
+    let response = IcyClient::from_uri(&format!("http://[::1]:{port}/;").parse().unwrap())
+        .unwrap()
+        .credentials("user", String::from("pass"))
+        .connect()
+        .unwrap();
+    assert!(response.is_icy());
+
+    let request = server.join().unwrap();
+    assert!(
+        request
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case(&format!("host: [::1]:{port}")))
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn icy_status_line_async() {
+    use tokio::io::AsyncReadExt;
+
+    let (port, _server) = serve_once("ICY 200 OK\r\nicy-metaint:4\r\n\r\n");
+
+    let response = IcyClient::new("127.0.0.1", port, "/")
+        .connect_async()
+        .await
+        .unwrap();
+    assert!(response.is_icy());
+    assert_eq!(response.icy_headers().metadata_interval().unwrap().get(), 4);
+
+    let mut body = Vec::new();
+    response.into_inner().read_to_end(&mut body).await.unwrap();
+    assert_eq!(body, icy_body());
+}
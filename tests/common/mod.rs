@@ -0,0 +1,14 @@
+use std::io::{self, Read};
+
+/// Reader that returns at most `limit` bytes at a time, like a socket.
+pub struct ShortReader<R> {
+    pub inner: R,
+    pub limit: usize,
+}
+
+impl<R: Read> Read for ShortReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.limit);
+        self.inner.read(&mut buf[..len])
+    }
+}
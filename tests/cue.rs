@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use icy_metadata::cue::CueSheet;
+use icy_metadata::{IcyHeaders, IcyMetadata};
+
+// MPEG 1 layer III, 128 kbps, 44.1 kHz, stereo
+const MP3_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+const MP3_FRAME_LEN: usize = 417;
+
+fn mp3_frames(count: usize) -> Vec<u8> {
+    let mut frame = MP3_HEADER.to_vec();
+    frame.resize(MP3_FRAME_LEN, 0);
+    frame.repeat(count)
+}
+
+#[test]
+fn bitrate_timestamps() {
+    let headers = IcyHeaders::default()
+        .with_name("My \"Station\"")
+        .with_bitrate(128);
+    let mut cue = CueSheet::new(&headers);
+    cue.add_track(0, &IcyMetadata::default().with_stream_title("Artist - One"));
+    // 65.5 seconds at 16000 bytes per second
+    cue.add_track(1_048_000, &IcyMetadata::default().with_stream_title("Two"));
+
+    assert_eq!(cue.tracks()[1].start(), Some(Duration::from_millis(65_500)));
+    let mut output = Vec::new();
+    cue.write_to(&mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        r#"TITLE "My 'Station'"
+FILE "My _Station_.mp3" MP3
+  TRACK 01 AUDIO
+    TITLE "One"
+    PERFORMER "Artist"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Two"
+    INDEX 01 01:05:37
+"#
+    );
+}
+
+#[test]
+fn frame_timestamps() {
+    let mut cue = CueSheet::new(&IcyHeaders::default()).file("show.mp3", "MP3");
+    cue.push_audio(&mp3_frames(60));
+    // Closest to the start of frame 50
+    cue.add_track(
+        (MP3_FRAME_LEN * 50 + 100) as u64,
+        &IcyMetadata::default().with_stream_title("Artist - One"),
+    );
+    // Audio hasn't been processed yet
+    let offset = (MP3_FRAME_LEN * 101 - 100) as u64;
+    cue.add_track(
+        offset,
+        &IcyMetadata::default().with_stream_title("Artist - Two"),
+    );
+    assert_eq!(cue.tracks()[1].start(), None);
+    cue.push_audio(&mp3_frames(60));
+
+    let frame_duration = Duration::from_secs(1152) / 44100;
+    assert_eq!(cue.tracks()[0].start(), Some(frame_duration * 50));
+    // Closest to the end of frame 100
+    assert_eq!(cue.tracks()[1].start(), Some(frame_duration * 101));
+    assert!(cue.to_string().contains("FILE \"show.mp3\" MP3\n"));
+    assert!(cue.to_string().contains("INDEX 01 00:01:22\n"));
+}
+
+#[test]
+fn track_limit() {
+    let mut cue = CueSheet::new(&IcyHeaders::default().with_bitrate(128));
+    for i in 0..99 {
+        cue.add_track(
+            i * 16000,
+            &IcyMetadata::default().with_stream_title("Track"),
+        );
+    }
+    let mut output = Vec::new();
+    cue.write_to(&mut output).unwrap();
+    assert!(
+        String::from_utf8(output)
+            .unwrap()
+            .contains("TRACK 99 AUDIO")
+    );
+
+    cue.add_track(
+        99 * 16000,
+        &IcyMetadata::default().with_stream_title("Extra"),
+    );
+    let err = cue.write_to(Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let output = cue.to_string();
+    assert!(!output.contains("TRACK 100"));
+    assert!(!output.contains("Extra"));
+}
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use icy_metadata::hls::{MediaPlaylist, segment_metadata};
+
+const TIMESTAMP_OWNER: &[u8] = b"com.apple.streaming.transportStreamTimestamp\0";
+const METADATA_PID: u16 = 0x102;
+
+fn id3_tag(frames: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (id, data) in frames {
+        body.extend_from_slice(id.as_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(data);
+    }
+    let mut tag = b"ID3\x03\x00\x00".to_vec();
+    let size = body.len();
+    tag.extend_from_slice(&[
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]);
+    tag.extend(body);
+    tag
+}
+
+fn title_frame(title: &str) -> (&'static str, Vec<u8>) {
+    let mut data = vec![3];
+    data.extend_from_slice(title.as_bytes());
+    ("TIT2", data)
+}
+
+fn adts_frame(len: usize) -> Vec<u8> {
+    // 44.1 kHz, single raw data block
+    let mut frame = vec![
+        0xFF,
+        0xF1,
+        0x50,
+        0x80 | ((len >> 11) as u8 & 0x03),
+        (len >> 3) as u8,
+        ((len as u8 & 0x07) << 5) | 0x1F,
+        0xFC,
+    ];
+    frame.resize(len, 0);
+    frame
+}
+
+fn ts_packet(pid: u16, payload_start: bool, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![
+        0x47,
+        if payload_start { 0x40 } else { 0 } | (pid >> 8) as u8,
+        pid as u8,
+    ];
+    let stuffing = 184 - payload.len();
+    if stuffing == 0 {
+        packet.push(0x10);
+    } else {
+        packet.push(0x30);
+        packet.push((stuffing - 1) as u8);
+        if stuffing > 1 {
+            packet.push(0);
+            packet.extend(vec![0xFF; stuffing - 2]);
+        }
+    }
+    packet.extend_from_slice(payload);
+    packet
+}
+
+fn pes_packet(pts: u64, payload: &[u8]) -> Vec<u8> {
+    let mut pes = vec![0, 0, 1, 0xBD, 0, 0, 0x84, 0x80, 5];
+    pes.extend_from_slice(&[
+        0x21 | ((pts >> 29) as u8 & 0x0E),
+        (pts >> 22) as u8,
+        ((pts >> 14) as u8 & 0xFE) | 1,
+        (pts >> 7) as u8,
+        ((pts << 1) as u8 & 0xFE) | 1,
+    ]);
+    pes.extend_from_slice(payload);
+    pes
+}
+
+fn transport_stream(pts: u64, tag: &[u8]) -> Vec<u8> {
+    let pat = [
+        0, 0x00, 0xB0, 13, 0, 1, 0xC1, 0, 0, 0, 1, 0xF0, 0x00, 0, 0, 0, 0,
+    ];
+    let pmt = [
+        0, 0x02, 0xB0, 18, 0, 1, 0xC1, 0, 0, 0xE1, 0x00, 0xF0, 0x00, 0x15, 0xE1, 0x02, 0xF0, 0x00,
+        0, 0, 0, 0,
+    ];
+    let mut segment = ts_packet(0, true, &pat);
+    segment.extend(ts_packet(0x1000, true, &pmt));
+    // Audio packet that should be ignored
+    segment.extend(ts_packet(0x100, true, &[0; 184]));
+    let pes = pes_packet(pts, tag);
+    for (i, chunk) in pes.chunks(184).enumerate() {
+        segment.extend(ts_packet(METADATA_PID, i == 0, chunk));
+    }
+    segment
+}
+
+#[test]
+fn media_playlist() {
+    let playlist: MediaPlaylist = include_str!("fixtures/media.m3u8").parse().unwrap();
+    assert_eq!(playlist.target_duration(), Some(Duration::from_secs(10)));
+    assert_eq!(playlist.media_sequence(), 1042);
+    assert!(!playlist.is_ended());
+
+    let segments = playlist.segments();
+    assert_eq!(segments.len(), 3);
+    assert_eq!(segments[0].uri(), "segment1042.aac");
+    assert_eq!(
+        segments[0].program_date_time(),
+        Some("2024-05-01T12:00:00.000Z")
+    );
+    let metadata = segments[0].metadata().unwrap();
+    assert_eq!(metadata.title(), Some("Song \"One\""));
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(
+        metadata.stream_url(),
+        Some("song_spot=\"M\" length=\"00:03:30\"")
+    );
+
+    assert_eq!(segments[1].sequence(), 1043);
+    assert_eq!(segments[1].duration(), Duration::from_secs_f64(9.984));
+    assert_eq!(
+        segments[1].metadata().unwrap().stream_title(),
+        Some("Other Artist - Second Song")
+    );
+    assert_eq!(segments[2].metadata(), None);
+
+    let date_range = &playlist.date_ranges()[0];
+    assert_eq!(date_range.id(), "track-2");
+    assert_eq!(date_range.class(), Some("com.example.track"));
+    assert_eq!(date_range.start_date(), "2024-05-01T12:00:10.000Z");
+    assert_eq!(date_range.duration(), Some(Duration::from_secs_f64(215.5)));
+    let metadata = date_range.metadata().unwrap();
+    assert_eq!(metadata.stream_title(), Some("Other Artist - Second Song"));
+    assert_eq!(metadata.custom_fields()["X-LABEL"], "label");
+}
+
+#[test]
+fn extinf_title_without_attributes() {
+    let playlist: MediaPlaylist = "#EXTM3U
+#EXTINF:10,E=MC2
+segment1.aac
+#EXTINF:10,mood=calm
+segment2.aac
+#EXTINF:10,mood=\"calm\"
+segment3.aac
+#EXTINF:10,Artist=Someone
+segment4.aac
+"
+    .parse()
+    .unwrap();
+    let segments = playlist.segments();
+    let metadata = segments[0].metadata().unwrap();
+    assert_eq!(metadata.stream_title(), Some("E=MC2"));
+    assert!(metadata.custom_fields().is_empty());
+    assert_eq!(
+        segments[1].metadata().unwrap().stream_title(),
+        Some("mood=calm")
+    );
+
+    let metadata = segments[2].metadata().unwrap();
+    assert_eq!(metadata.stream_title(), None);
+    assert_eq!(metadata.custom_fields()["mood"], "calm");
+    assert_eq!(segments[3].metadata().unwrap().artist(), Some("Someone"));
+}
+
+#[test]
+fn invalid_playlist() {
+    assert!("segment.aac".parse::<MediaPlaylist>().is_err());
+    assert!(
+        "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=128000\nlow.m3u8"
+            .parse::<MediaPlaylist>()
+            .is_err()
+    );
+}
+
+#[test]
+fn packed_audio() {
+    let mut timestamp = TIMESTAMP_OWNER.to_vec();
+    timestamp.extend_from_slice(&900_000u64.to_be_bytes());
+
+    let mut segment = id3_tag(&[("PRIV", timestamp)]);
+    segment.extend(adts_frame(20));
+    segment.extend(adts_frame(30));
+    segment.extend(id3_tag(&[title_frame("title")]));
+    segment.extend(adts_frame(20));
+
+    let events = segment_metadata(&segment);
+    assert_eq!(events.len(), 1);
+    let event = events[0].as_ref().unwrap();
+    // Two frames of 1024 samples at 44.1 kHz
+    assert_eq!(event.pts(), Some(900_000 + 2 * 2089));
+    assert_eq!(event.metadata().title(), Some("title"));
+    assert!(event.metadata().custom_fields().is_empty());
+}
+
+#[test]
+fn transport_stream_segment() {
+    let title = "a".repeat(200);
+    let segment = transport_stream(180_000, &id3_tag(&[title_frame(&title)]));
+
+    let events = segment_metadata(&segment);
+    assert_eq!(events.len(), 1);
+    let event = events[0].as_ref().unwrap();
+    assert_eq!(event.pts(), Some(180_000));
+    assert_eq!(event.presentation_time(), Some(Duration::from_secs(2)));
+    assert_eq!(event.metadata().title(), Some(title.as_str()));
+}
@@ -0,0 +1,255 @@
+use std::io::Read;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use icy_metadata::error::MetadataParseError;
+use icy_metadata::id3::Id3Reader;
+use icy_metadata::{Artwork, ArtworkKind, IcyHeaders, IcyMetadata, IcyMetadataReader};
+use rstest::rstest;
+
+fn syncsafe(size: usize) -> [u8; 4] {
+    [
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]
+}
+
+fn tag(major: u8, flags: u8, frames: &[Vec<u8>]) -> Vec<u8> {
+    let body = frames.concat();
+    let mut tag = vec![b'I', b'D', b'3', major, 0, flags];
+    tag.extend_from_slice(&syncsafe(body.len()));
+    tag.extend(body);
+    tag
+}
+
+fn frame(major: u8, id: &str, data: &[u8]) -> Vec<u8> {
+    let mut frame = id.as_bytes().to_vec();
+    match major {
+        2 => frame.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]),
+        3 => frame.extend_from_slice(&(data.len() as u32).to_be_bytes()),
+        _ => frame.extend_from_slice(&syncsafe(data.len())),
+    }
+    if major > 2 {
+        frame.extend_from_slice(&[0, 0]);
+    }
+    frame.extend_from_slice(data);
+    frame
+}
+
+fn text(encoding: u8, value: &[u8]) -> Vec<u8> {
+    let mut data = vec![encoding];
+    data.extend_from_slice(value);
+    data
+}
+
+fn utf16(value: &str) -> Vec<u8> {
+    let mut data = vec![0xFF, 0xFE];
+    data.extend(value.encode_utf16().flat_map(u16::to_le_bytes));
+    data
+}
+
+fn v3_tag() -> Vec<u8> {
+    let mut apic = vec![0];
+    apic.extend_from_slice(b"image/png\0");
+    apic.push(3);
+    apic.extend_from_slice(b"cover\0");
+    apic.extend_from_slice(&[1, 2, 3]);
+
+    tag(
+        3,
+        0,
+        &[
+            frame(3, "TIT2", &text(0, b"title")),
+            frame(3, "TPE1", &text(1, &utf16("art\u{ed}st"))),
+            frame(3, "TXXX", &text(3, b"station\0my station")),
+            frame(3, "APIC", &apic),
+            frame(3, "PRIV", b"owner\0\x00\x01"),
+            frame(3, "WOAS", b"http://example.com"),
+        ],
+    )
+}
+
+#[test]
+fn parse_v3() {
+    let metadata = IcyMetadata::from_id3(&v3_tag()).unwrap();
+    assert_eq!(metadata.title(), Some("title"));
+    assert_eq!(metadata.artist(), Some("art\u{ed}st"));
+    assert_eq!(metadata.stream_title(), Some("art\u{ed}st - title"));
+
+    let custom = metadata.custom_fields();
+    assert_eq!(custom["station"], "my station");
+    assert_eq!(custom["owner"], "AAE=");
+    assert_eq!(custom["WOAS"], "http://example.com");
+
+    let artwork = &metadata.artwork()[0];
+    assert_eq!(artwork.kind(), ArtworkKind::Track);
+    assert_eq!(artwork.mime_type(), "image/png");
+    assert_eq!(artwork.data(), &[1, 2, 3]);
+}
+
+#[test]
+fn parse_v4() {
+    let mut album = frame(4, "TALB", &text(3, b"\xFF\x00album"));
+    // Unsynchronized frame with a data length indicator
+    album[9] = 0x03;
+    album.splice(10..10, syncsafe(7));
+    let len = album.len() - 10;
+    album.splice(4..8, syncsafe(len));
+
+    let tag = tag(
+        4,
+        0,
+        &[
+            frame(4, "TPE1", &text(3, b"first\0second")),
+            album,
+            vec![0; 10],
+        ],
+    );
+    let metadata = IcyMetadata::from_id3(&tag).unwrap();
+    assert_eq!(metadata.artist(), Some("first, second"));
+    assert_eq!(metadata.album(), Some("\u{fffd}album"));
+}
+
+#[test]
+fn parse_v2() {
+    let mut pic = vec![0];
+    pic.extend_from_slice(b"JPG");
+    pic.push(20);
+    pic.extend_from_slice(b"\0");
+    pic.extend_from_slice(&[7, 8]);
+
+    let tag = tag(
+        2,
+        0,
+        &[frame(2, "TT2", &text(0, b"title")), frame(2, "PIC", &pic)],
+    );
+    let metadata = IcyMetadata::from_id3(&tag).unwrap();
+    assert_eq!(metadata.title(), Some("title"));
+    let artwork = &metadata.artwork()[0];
+    assert_eq!(artwork.kind(), ArtworkKind::Station);
+    assert_eq!(artwork.mime_type(), "image/jpeg");
+}
+
+#[test]
+fn invalid_tag() {
+    let mut tag = v3_tag();
+    tag.truncate(20);
+    let err = IcyMetadata::from_id3(&tag).unwrap_err();
+    assert!(matches!(err, MetadataParseError::InvalidId3(_)));
+    assert_eq!(
+        err.to_string(),
+        "Failed to parse ID3 tag: Invalid ID3 tag: tag is truncated"
+    );
+}
+
+#[test]
+fn write_tag() {
+    let metadata = IcyMetadata::default()
+        .with_stream_title("Art\u{ed}st - Title")
+        .with_stream_url("http://example.com/track")
+        .with_artwork(Artwork::new(ArtworkKind::Track, "image/png", vec![1, 2, 3]));
+    let headers = IcyHeaders::default()
+        .with_name("Station")
+        .with_genre(["Rock", "Pop"]);
+    let tag = metadata.to_id3(Some(&headers));
+    assert_eq!(&tag[..5], b"ID3\x04\x00");
+
+    let parsed = IcyMetadata::from_id3(&tag).unwrap();
+    assert_eq!(parsed.artist(), Some("Art\u{ed}st"));
+    assert_eq!(parsed.title(), Some("Title"));
+    assert_eq!(parsed.stream_title(), Some("Art\u{ed}st - Title"));
+    let custom = parsed.custom_fields();
+    assert_eq!(custom["WXXX"], "http://example.com/track");
+    assert_eq!(custom["TRSN"], "Station");
+    assert_eq!(custom["TCON"], "Rock, Pop");
+    assert_eq!(parsed.artwork(), metadata.artwork());
+
+    // Structured values are written as-is without station information
+    let tag = parsed.to_id3(None);
+    let reparsed = IcyMetadata::from_id3(&tag).unwrap();
+    assert_eq!(reparsed.title(), Some("Title"));
+    assert_eq!(reparsed.custom_fields().get("TRSN"), None);
+
+    let tag = IcyMetadata::default()
+        .with_stream_title("Title - Only - Dashes")
+        .to_id3(None);
+    let parsed = IcyMetadata::from_id3(&tag).unwrap();
+    assert_eq!(parsed.artist(), Some("Title"));
+    assert_eq!(parsed.title(), Some("Only - Dashes"));
+}
+
+fn read_all<R: Read>(mut reader: R, chunk_size: usize) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut buf = vec![0; chunk_size];
+    loop {
+        let read = reader.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..read]);
+    }
+    output
+}
+
+#[rstest]
+fn reader(#[values(1, 5, 4096)] chunk_size: usize, #[values(true, false)] strip_tags: bool) {
+    let audio = [1u8, b'I', b'D', 2, b'I', 3];
+    let tag = v3_tag();
+    let mut data = audio.to_vec();
+    data.extend(&tag);
+    data.extend(audio);
+    data.extend(&tag);
+    data.extend(b"ID");
+
+    let metadata = Arc::new(Mutex::new(Vec::new()));
+    let reader = {
+        let metadata = metadata.clone();
+        Id3Reader::new(&data[..], move |meta| {
+            metadata.lock().unwrap().push(meta.unwrap());
+        })
+        .strip_tags(strip_tags)
+    };
+    let output = read_all(reader, chunk_size);
+    if strip_tags {
+        let mut expected = audio.to_vec();
+        expected.extend(audio);
+        expected.extend(b"ID");
+        assert_eq!(output, expected);
+    } else {
+        assert_eq!(output, data);
+    }
+
+    let metadata = metadata.lock().unwrap();
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(metadata[1].title(), Some("title"));
+}
+
+#[test]
+fn stacked_with_icy_reader() {
+    let mut audio = vec![0; 3];
+    audio.extend(v3_tag());
+    audio.extend([0; 5]);
+
+    let metaint = 8;
+    let mut data = Vec::new();
+    for chunk in audio.chunks(metaint) {
+        data.extend_from_slice(chunk);
+        if chunk.len() == metaint {
+            data.push(0);
+        }
+    }
+
+    let metadata = Arc::new(Mutex::new(Vec::new()));
+    let icy_reader = IcyMetadataReader::new(&data[..], NonZeroUsize::new(metaint), |_| {});
+    let reader = {
+        let metadata = metadata.clone();
+        Id3Reader::new(icy_reader, move |meta| {
+            metadata.lock().unwrap().push(meta.unwrap());
+        })
+        .strip_tags(true)
+    };
+    assert_eq!(read_all(reader, 64), vec![0; 8]);
+    assert_eq!(metadata.lock().unwrap()[0].title(), Some("title"));
+}
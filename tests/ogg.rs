@@ -0,0 +1,269 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use icy_metadata::ogg::{OggMetadataReader, PageHeader};
+use icy_metadata::{ArtworkKind, IcyMetadata};
+
+const CONTINUED: u8 = 0x01;
+const BOS: u8 = 0x02;
+const EOS: u8 = 0x04;
+
+fn page(flags: u8, serial: u32, sequence: u32, segments: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut page = b"OggS".to_vec();
+    page.push(0);
+    page.push(flags);
+    page.extend_from_slice(&0u64.to_le_bytes());
+    page.extend_from_slice(&serial.to_le_bytes());
+    page.extend_from_slice(&sequence.to_le_bytes());
+    page.extend_from_slice(&[0; 4]);
+    page.push(segments.len() as u8);
+    page.extend_from_slice(segments);
+    page.extend_from_slice(body);
+    let checksum = checksum(&page);
+    page[22..26].copy_from_slice(&checksum.to_le_bytes());
+    page
+}
+
+fn checksum(page: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for byte in page {
+        crc ^= u32::from(*byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn lacing(len: usize) -> Vec<u8> {
+    let mut segments = vec![255; len / 255];
+    segments.push((len % 255) as u8);
+    segments
+}
+
+fn comments(comments: &[&str]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    let vendor = b"test";
+    packet.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    packet.extend_from_slice(vendor);
+    packet.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        packet.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        packet.extend_from_slice(comment.as_bytes());
+    }
+    packet
+}
+
+fn comment_packet(list: &[&str]) -> Vec<u8> {
+    let mut packet = b"\x03vorbis".to_vec();
+    packet.extend(comments(list));
+    packet.push(1);
+    packet
+}
+
+fn picture(picture_type: u32, mime_type: &str, data: &[u8]) -> Vec<u8> {
+    let mut picture = picture_type.to_be_bytes().to_vec();
+    picture.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    picture.extend_from_slice(mime_type.as_bytes());
+    picture.extend_from_slice(&0u32.to_be_bytes());
+    picture.extend_from_slice(&[0; 16]);
+    picture.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    picture.extend_from_slice(data);
+    picture
+}
+
+fn flac_block(block_type: u8, last: bool, data: &[u8]) -> Vec<u8> {
+    let mut block = vec![block_type | if last { 0x80 } else { 0 }];
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    block.extend_from_slice(data);
+    block
+}
+
+fn packets_page(flags: u8, serial: u32, sequence: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+    let segments: Vec<_> = packets.iter().flat_map(|p| lacing(p.len())).collect();
+    page(flags, serial, sequence, &segments, &packets.concat())
+}
+
+fn vorbis_stream(serial: u32, comments: &[&str]) -> Vec<u8> {
+    let ident = b"\x01vorbis-ident".to_vec();
+    let mut stream = page(BOS, serial, 0, &lacing(ident.len()), &ident);
+    let comments = comment_packet(comments);
+    stream.extend(page(0, serial, 1, &lacing(comments.len()), &comments));
+    stream.extend(page(EOS, serial, 2, &[4], &[9; 4]));
+    stream
+}
+
+fn read_all(data: Vec<u8>, chunk_size: usize) -> (Vec<u8>, Vec<IcyMetadata>) {
+    let metadata = Arc::new(Mutex::new(Vec::new()));
+    let mut reader = {
+        let metadata = metadata.clone();
+        OggMetadataReader::new(&data[..], move |meta| {
+            metadata.lock().unwrap().push(meta.unwrap());
+        })
+    };
+    let mut output = Vec::new();
+    let mut buf = vec![0; chunk_size];
+    loop {
+        let read = reader.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..read]);
+    }
+    let metadata = metadata.lock().unwrap().clone();
+    (output, metadata)
+}
+
+#[test]
+fn page_header() {
+    let data = page(BOS | EOS, 1234, 5, &[3, 2], &[0; 5]);
+    let header = PageHeader::parse(&data).unwrap();
+    assert!(header.flags().is_beginning_of_stream());
+    assert!(header.flags().is_end_of_stream());
+    assert!(!header.flags().is_continued());
+    assert_eq!(header.serial(), 1234);
+    assert_eq!(header.sequence(), 5);
+    assert_eq!(header.header_len(), 29);
+    assert_eq!(header.body_len(), 5);
+    assert_eq!(
+        header.checksum(),
+        u32::from_le_bytes(data[22..26].try_into().unwrap())
+    );
+}
+
+#[test]
+fn invalid_checksum() {
+    let mut first = vorbis_stream(1, &["TITLE=corrupt"]);
+    // Corrupt the comment page body
+    let last = first.len() - 40;
+    first[last] ^= 0xFF;
+    let mut data = first;
+    data.extend(vorbis_stream(2, &["TITLE=valid"]));
+
+    for chunk_size in [1, 4096] {
+        let (output, metadata) = read_all(data.clone(), chunk_size);
+        assert_eq!(output, data);
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].title(), Some("valid"));
+    }
+}
+
+#[test]
+fn chained_stream_without_eos() {
+    let ident = b"\x01vorbis-ident".to_vec();
+    let comments = comment_packet(&["TITLE=stale"]);
+    // The first stream ends before its comment header is sent
+    let mut data = page(BOS, 1, 0, &lacing(ident.len()), &ident);
+    data.extend(page(BOS, 2, 0, &lacing(ident.len()), &ident));
+    data.extend(page(0, 2, 1, &[4], &[9; 4]));
+    data.extend(vorbis_stream(3, &["TITLE=next"]));
+    // A leftover page from the first stream shouldn't be treated as its comment header
+    data.extend(page(0, 1, 1, &lacing(comments.len()), &comments));
+
+    let (output, metadata) = read_all(data.clone(), 4096);
+    assert_eq!(output, data);
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].title(), Some("next"));
+}
+
+#[test]
+fn chained_streams() {
+    let mut data = vorbis_stream(1, &["TITLE=first", "ARTIST=artist", "ALBUM=album"]);
+    data.extend(vorbis_stream(2, &["title=second", "genre=rock"]));
+
+    for chunk_size in [1, 7, 4096] {
+        let (output, metadata) = read_all(data.clone(), chunk_size);
+        assert_eq!(output, data);
+        assert_eq!(metadata.len(), 2);
+
+        assert_eq!(metadata[0].title(), Some("first"));
+        assert_eq!(metadata[0].artist(), Some("artist"));
+        assert_eq!(metadata[0].album(), Some("album"));
+        assert_eq!(metadata[0].stream_title(), Some("artist - first"));
+
+        assert_eq!(metadata[1].title(), Some("second"));
+        assert_eq!(metadata[1].stream_title(), Some("second"));
+        assert_eq!(
+            metadata[1].custom_fields().get("GENRE").map(String::as_str),
+            Some("rock")
+        );
+    }
+}
+
+#[test]
+fn comment_spanning_pages() {
+    let long_title = format!("TITLE={}", "a".repeat(600));
+    let comments = comment_packet(&[&long_title]);
+    let (first, second) = comments.split_at(255);
+
+    let ident = b"\x01vorbis-ident".to_vec();
+    let mut data = b"garbage".to_vec();
+    data.extend(page(BOS, 7, 0, &lacing(ident.len()), &ident));
+    data.extend(page(0, 7, 1, &[255], first));
+    data.extend(page(CONTINUED, 7, 2, &lacing(second.len()), second));
+
+    let (output, metadata) = read_all(data.clone(), 64);
+    assert_eq!(output, data);
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].title(), Some("a".repeat(600).as_str()));
+}
+
+#[test]
+fn opus_tags() {
+    let cover = picture(3, "image/png", &[1, 2, 3]);
+    let picture_comment = format!("METADATA_BLOCK_PICTURE={}", BASE64_STANDARD.encode(&cover));
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend(comments(&[
+        "ARTIST=artist",
+        "TITLE=title",
+        &picture_comment,
+    ]));
+
+    let mut data = packets_page(BOS, 3, 0, &[b"OpusHead-ident".to_vec()]);
+    data.extend(packets_page(0, 3, 1, &[tags]));
+    data.extend(page(0, 3, 2, &[4], &[9; 4]));
+
+    let (output, metadata) = read_all(data.clone(), 16);
+    assert_eq!(output, data);
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].stream_title(), Some("artist - title"));
+    assert!(metadata[0].custom_fields().is_empty());
+
+    let artwork = &metadata[0].artwork()[0];
+    assert_eq!(artwork.kind(), ArtworkKind::Track);
+    assert_eq!(artwork.mime_type(), "image/png");
+    assert_eq!(artwork.data(), &[1, 2, 3]);
+}
+
+#[test]
+fn flac_metadata_blocks() {
+    let mut mapping = b"\x7fFLAC\x01\x00\x00\x03fLaC".to_vec();
+    mapping.extend(flac_block(0, false, &[0; 34]));
+    let blocks = [
+        flac_block(4, false, &comments(&["TITLE=flac title", "ALBUM=album"])),
+        flac_block(1, false, &[0; 8]),
+        flac_block(6, true, &picture(20, "image/jpeg", &[4, 5])),
+    ];
+
+    let mut data = packets_page(BOS, 9, 0, &[mapping]);
+    data.extend(packets_page(0, 9, 1, &blocks));
+    data.extend(page(EOS, 9, 2, &[4], &[9; 4]));
+
+    let (output, metadata) = read_all(data.clone(), 4096);
+    assert_eq!(output, data);
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(metadata[0].title(), Some("flac title"));
+    assert_eq!(metadata[0].album(), Some("album"));
+    assert_eq!(metadata[0].stream_title(), Some("flac title"));
+
+    let artwork = &metadata[0].artwork()[0];
+    assert_eq!(artwork.kind(), ArtworkKind::Station);
+    assert_eq!(artwork.mime_type(), "image/jpeg");
+    assert_eq!(artwork.data(), &[4, 5]);
+}
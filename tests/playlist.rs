@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use icy_metadata::playlist::{Playlist, PlaylistFormat, Resolved, resolve};
+use rstest::rstest;
+
+const PLS: &str = "[playlist]
+NumberOfEntries=2
+File1=http://example.com:8000/stream
+Title1=(#1 - 1/100) \
+                   Station
+Length1=-1
+File2=http://backup.example.com/stream
+Length2=120
+Version=2
+";
+
+const M3U: &str = r#"#EXTM3U
+#PLAYLIST:Stations
+#EXTINF:-1 tvg-id="station",Station
+http://example.com/stream.mp3
+# comment
+http://backup.example.com/stream.mp3
+"#;
+
+const XSPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Stations</title>
+  <trackList>
+    <track>
+      <location>http://example.com/stream.ogg</location>
+      <location>http://backup.example.com/stream.ogg</location>
+      <title>Station</title>
+      <duration>90000</duration>
+    </track>
+  </trackList>
+</playlist>"#;
+
+const ASX: &str = r#"<ASX version="3.0">
+  <TITLE>Stations</TITLE>
+  <Entry>
+    <Title>Station</Title>
+    <Duration value="00:01:30.5" />
+    <Ref href="mms://example.com/stream?a=1&b=2" />
+    <REF HREF="http://example.com/stream" />
+  </entry>
+  <EntryRef href="http://example.com/more.asx" />
+</ASX>"#;
+
+#[test]
+fn pls() {
+    let playlist = Playlist::parse(PlaylistFormat::Pls, PLS).unwrap();
+    let entries = playlist.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].url(), "http://example.com:8000/stream");
+    assert_eq!(entries[0].title(), Some("(#1 - 1/100) Station"));
+    assert_eq!(entries[0].length(), None);
+    assert_eq!(entries[1].length(), Some(Duration::from_secs(120)));
+}
+
+#[test]
+fn m3u() {
+    let playlist = Playlist::parse(PlaylistFormat::M3u, M3U).unwrap();
+    assert_eq!(playlist.title(), Some("Stations"));
+    let entries = playlist.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].title(), Some("Station"));
+    assert_eq!(entries[1].url(), "http://backup.example.com/stream.mp3");
+    assert_eq!(entries[1].title(), None);
+}
+
+#[test]
+fn xspf() {
+    let playlist = Playlist::parse(PlaylistFormat::Xspf, XSPF).unwrap();
+    assert_eq!(playlist.title(), Some("Stations"));
+    let entries = playlist.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].url(), "http://backup.example.com/stream.ogg");
+    assert_eq!(entries[1].title(), Some("Station"));
+    assert_eq!(entries[1].length(), Some(Duration::from_secs(90)));
+}
+
+#[test]
+fn asx() {
+    let playlist = Playlist::parse(PlaylistFormat::Asx, ASX).unwrap();
+    assert_eq!(playlist.title(), Some("Stations"));
+    let entries = playlist.entries();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].url(), "mms://example.com/stream?a=1&b=2");
+    assert_eq!(entries[0].length(), Some(Duration::from_secs_f64(90.5)));
+    assert_eq!(entries[1].title(), Some("Station"));
+    assert_eq!(entries[2].url(), "http://example.com/more.asx");
+
+    let reference = "[Reference]\r\nRef1=http://example.com/stream?MSWMExt=.asf\r\n";
+    let playlist = Playlist::parse(PlaylistFormat::Asx, reference).unwrap();
+    assert_eq!(
+        playlist.entries()[0].url(),
+        "http://example.com/stream?MSWMExt=.asf"
+    );
+}
+
+#[test]
+fn empty_playlist() {
+    assert!(Playlist::parse(PlaylistFormat::Pls, "[playlist]\nNumberOfEntries=0").is_err());
+}
+
+#[rstest]
+#[case(Some("audio/x-scpls"), PLS.as_bytes(), Some(PlaylistFormat::Pls))]
+#[case(Some("text/plain"), PLS.as_bytes(), Some(PlaylistFormat::Pls))]
+#[case(Some("audio/x-mpegurl; charset=utf-8"), M3U.as_bytes(), Some(PlaylistFormat::M3u))]
+#[case(
+    Some("audio/mpegurl"),
+    b"http://example.com/stream\n",
+    Some(PlaylistFormat::M3u)
+)]
+#[case(None, b"http://example.com/stream\r\n", Some(PlaylistFormat::M3u))]
+#[case(Some("application/octet-stream"), XSPF.as_bytes(), Some(PlaylistFormat::Xspf))]
+#[case(Some("video/x-ms-asf"), ASX.as_bytes(), Some(PlaylistFormat::Asx))]
+#[case(Some("video/x-ms-asf"), &[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0], None)]
+#[case(Some("audio/mpeg"), &[0xFF, 0xFB, 0x90, 0x64], None)]
+#[case(Some("audio/aacp"), b"http://not-a-playlist", None)]
+fn resolve_format(
+    #[case] content_type: Option<&str>,
+    #[case] body: &[u8],
+    #[case] expected: Option<PlaylistFormat>,
+) {
+    match resolve(content_type, body).unwrap() {
+        Resolved::Playlist(playlist) => assert_eq!(Some(playlist.format()), expected),
+        Resolved::Stream => assert_eq!(None, expected),
+        Resolved::Hls => panic!("unexpected HLS playlist"),
+    }
+}
+
+#[test]
+fn resolve_hls() {
+    let body = b"#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nsegment.aac\n";
+    assert_eq!(
+        resolve(Some("application/vnd.apple.mpegurl"), body).unwrap(),
+        Resolved::Hls
+    );
+}
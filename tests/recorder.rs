@@ -0,0 +1,303 @@
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use icy_metadata::frame::{FrameHeader, FrameKind};
+use icy_metadata::recorder::StreamRecorder;
+use icy_metadata::{IcyHeaders, IcyMetadata};
+
+use crate::common::ShortReader;
+
+mod common;
+
+const METAINT: usize = 16;
+
+fn output_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("icy-recorder-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn metadata_block(title: Option<&str>) -> Vec<u8> {
+    let Some(title) = title else {
+        return vec![0];
+    };
+    let mut metadata = format!("StreamTitle='{title}';").into_bytes();
+    let blocks = metadata.len().div_ceil(16);
+    metadata.resize(blocks * 16, 0);
+    let mut block = vec![blocks as u8];
+    block.extend(metadata);
+    block
+}
+
+/// Builds a stream where each chunk of audio is filled with its index and followed by the given
+/// metadata.
+fn stream(titles: &[Option<&str>]) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for (i, title) in titles.iter().enumerate() {
+        stream.extend(vec![i as u8; METAINT]);
+        stream.extend(metadata_block(*title));
+    }
+    stream
+}
+
+// MPEG 1 layer III, 128 kbps, 44.1 kHz, stereo
+const MP3_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+const MP3_FRAME_LEN: usize = 417;
+
+fn mp3_frame(fill: u8) -> Vec<u8> {
+    let mut frame = MP3_HEADER.to_vec();
+    frame.resize(MP3_FRAME_LEN, fill);
+    frame
+}
+
+/// Inserts metadata into `audio` every `metaint` bytes.
+fn insert_metadata(audio: &[u8], metaint: usize, titles: &[Option<&str>]) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for (i, chunk) in audio.chunks(metaint).enumerate() {
+        stream.extend_from_slice(chunk);
+        if chunk.len() == metaint {
+            stream.extend(metadata_block(titles.get(i).copied().flatten()));
+        }
+    }
+    stream
+}
+
+fn headers() -> IcyHeaders {
+    headers_with_metaint(METAINT)
+}
+
+fn headers_with_metaint(metaint: usize) -> IcyHeaders {
+    let mut header_map = http::HeaderMap::new();
+    header_map.insert("icy-metaint", metaint.to_string().parse().unwrap());
+    // 1 kbps, 125 bytes per second
+    header_map.insert("icy-br", "1".parse().unwrap());
+    IcyHeaders::parse_from_headers(&header_map)
+}
+
+#[test]
+fn split_tracks() {
+    let dir = output_dir("split");
+    let data = stream(&[
+        Some("Artist - First"),
+        Some("Artist - First"),
+        None,
+        Some("AC/DC - Second: Part 2"),
+        Some("Artist - First"),
+    ]);
+    let mut recorder = StreamRecorder::new(Cursor::new(data), &headers(), &dir);
+    let mut passthrough = Vec::new();
+    // Use an odd buffer size to make sure reads spanning metadata blocks are handled
+    let mut buf = [0; 7];
+    loop {
+        let read = recorder.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        passthrough.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(passthrough.len(), 5 * METAINT);
+
+    // The audio before the first metadata block is included in the first track
+    let first = fs::read(dir.join("Artist - First.mp3")).unwrap();
+    assert_eq!(
+        first,
+        [
+            vec![0; METAINT],
+            vec![1; METAINT],
+            vec![2; METAINT],
+            vec![3; METAINT]
+        ]
+        .concat()
+    );
+    let second = fs::read(dir.join("AC_DC - Second_ Part 2.mp3")).unwrap();
+    assert_eq!(second, vec![4; METAINT]);
+    // The same track playing again shouldn't overwrite the existing file
+    assert!(dir.join("Artist - First (2).mp3").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn truncate_long_names() {
+    let dir = output_dir("long-names");
+    let artist = "Artist ".repeat(20);
+    let title = "Tïtle ".repeat(20);
+    let data = stream(&[Some(&format!("{artist}- {title}")), None]);
+    StreamRecorder::new(Cursor::new(data), &headers(), &dir)
+        .record()
+        .unwrap();
+
+    let files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files.len(), 1);
+    assert!(files[0].len() <= 200);
+    assert!(files[0].starts_with("Artist Artist"));
+    assert!(files[0].ends_with(".mp3"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn short_inner_reads() {
+    let dir = output_dir("short-reads");
+    let metaint = 1000;
+    let audio: Vec<_> = (0..4).flat_map(|i| vec![i as u8; metaint]).collect();
+    let data = insert_metadata(
+        &audio,
+        metaint,
+        &[
+            Some("Artist - First"),
+            Some("Artist - First"),
+            Some("Artist - Second"),
+        ],
+    );
+    let inner = ShortReader {
+        inner: Cursor::new(data),
+        limit: 100,
+    };
+    let mut recorder = StreamRecorder::new(inner, &headers_with_metaint(metaint), &dir);
+    let mut passthrough = Vec::new();
+    let mut buf = [0; 512];
+    loop {
+        let read = recorder.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        passthrough.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(passthrough, audio);
+
+    let first = fs::read(dir.join("Artist - First.mp3")).unwrap();
+    assert_eq!(
+        first,
+        [vec![0; metaint], vec![1; metaint], vec![2; metaint]].concat()
+    );
+    let second = fs::read(dir.join("Artist - Second.mp3")).unwrap();
+    assert_eq!(second, vec![3; metaint]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn skip_first_track_with_padding() {
+    let dir = output_dir("padding");
+    let data = stream(&[Some("One"), None, Some("Two"), None, Some("Three"), None]);
+    let mut recorder = StreamRecorder::new(Cursor::new(data), &headers(), &dir)
+        .template("{index} {title}.aac")
+        .skip_first_track(true)
+        // 4 bytes at 125 bytes per second
+        .pre_padding(Duration::from_millis(32))
+        .post_padding(Duration::from_millis(32));
+    recorder.record().unwrap();
+
+    let files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files.len(), 2);
+
+    let two = fs::read(dir.join("001 Two.aac")).unwrap();
+    assert_eq!(
+        two,
+        [vec![2; 4], vec![3; METAINT], vec![4; METAINT], vec![5; 4]].concat()
+    );
+    let three = fs::read(dir.join("002 Three.aac")).unwrap();
+    assert_eq!(three, [vec![4; 4], vec![5; METAINT]].concat());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn frame_header() {
+    let header = FrameHeader::parse(&mp3_frame(0)).unwrap();
+    assert_eq!(header.kind(), FrameKind::Mpeg);
+    assert_eq!(header.layer(), Some(3));
+    assert_eq!(header.frame_len(), MP3_FRAME_LEN);
+    assert_eq!(header.bitrate(), Some(128));
+    assert_eq!(header.sample_rate(), 44100);
+    assert_eq!(header.samples(), 1152);
+
+    // 48 kHz, 1 raw data block, 200 byte frame
+    let adts = [0xFF, 0xF1, 0x4C, 0x80, 0x19, 0x1F, 0xFC];
+    let header = FrameHeader::parse(&adts).unwrap();
+    assert_eq!(header.kind(), FrameKind::Adts);
+    assert_eq!(header.frame_len(), 200);
+    assert_eq!(header.duration(), Duration::from_secs(1024) / 48000);
+
+    assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x00]).is_none());
+}
+
+#[test]
+fn align_to_frames() {
+    let dir = output_dir("align");
+    let audio: Vec<u8> = (0..10).flat_map(|i| mp3_frame(i + 1)).collect();
+    // Boundaries at 1000, 2000 and 3000 bytes fall inside the third, fifth and eighth frames
+    let data = insert_metadata(
+        &audio,
+        1000,
+        &[Some("A - One"), Some("B - Two"), Some("C - Three"), None],
+    );
+    StreamRecorder::new(Cursor::new(data), &headers_with_metaint(1000), &dir)
+        .template("{title}.mp3")
+        .record()
+        .unwrap();
+
+    let frames = |file: &str| {
+        let data = fs::read(dir.join(file)).unwrap();
+        assert_eq!(data.len() % MP3_FRAME_LEN, 0);
+        data.chunks(MP3_FRAME_LEN)
+            .map(|frame| frame[4])
+            .collect::<Vec<_>>()
+    };
+    // 1000 is closer to the start of the third frame at 834, the frames before it are the lead-in
+    assert_eq!(frames("One.mp3"), [1, 2, 3, 4, 5]);
+    // 2000 is closer to the end of the fifth frame at 2085
+    assert_eq!(frames("Two.mp3"), [6, 7]);
+    assert_eq!(frames("Three.mp3"), [8, 9, 10]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn xing_header() {
+    let dir = output_dir("xing");
+    let audio: Vec<u8> = (0..6).flat_map(|i| mp3_frame(i + 1)).collect();
+    let data = insert_metadata(&audio, 1000, &[Some("One"), None]);
+    StreamRecorder::new(Cursor::new(data), &headers_with_metaint(1000), &dir)
+        .template("{title}.mp3")
+        .write_xing_header(true)
+        .record()
+        .unwrap();
+
+    let data = fs::read(dir.join("One.mp3")).unwrap();
+    assert_eq!(data.len(), 7 * MP3_FRAME_LEN);
+    let xing = &data[..MP3_FRAME_LEN];
+    assert_eq!(&xing[..4], MP3_HEADER);
+    // Stereo MPEG 1 has 32 bytes of side info
+    let tag = &xing[36..52];
+    assert_eq!(&tag[..4], b"Info");
+    assert_eq!(&tag[4..8], 3u32.to_be_bytes());
+    assert_eq!(&tag[8..12], 6u32.to_be_bytes());
+    assert_eq!(&tag[12..16], (7 * MP3_FRAME_LEN as u32).to_be_bytes());
+    assert_eq!(data[MP3_FRAME_LEN + 4], 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn id3_tags() {
+    let dir = output_dir("id3");
+    let data = stream(&[Some("Artist - First"), None]);
+    let headers = headers().with_name("Station");
+    StreamRecorder::new(Cursor::new(data), &headers, &dir)
+        .write_id3_tags(true)
+        .record()
+        .unwrap();
+
+    let data = fs::read(dir.join("Artist - First.mp3")).unwrap();
+    let (tag, audio) = data.split_at(data.len() - 2 * METAINT);
+    assert_eq!(audio, [vec![0; METAINT], vec![1; METAINT]].concat());
+    let metadata = IcyMetadata::from_id3(tag).unwrap();
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(metadata.title(), Some("First"));
+    assert_eq!(metadata.custom_fields()["TRSN"], "Station");
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, SystemTime};
+
+use icy_metadata::status::{IcecastStatus, PlayedAt, ShoutcastStats, SongHistory};
+use icy_metadata::{IcyHeaders, IcyMetadata};
+
+#[cfg(feature = "serde")]
+const SINGLE_SOURCE_JSON: &str = r#"{
+  "icestats": {
+    "admin": "admin@example.com",
+    "host": "example.com",
+    "location": "Earth",
+    "server_id": "Icecast 2.4.4",
+    "server_start_iso8601": "2024-05-01T12:00:00+0000",
+    "source": {
+      "audio_info": "channels=2;samplerate=44100;bitrate=128",
+      "bitrate": 128,
+      "genre": "Rock,Pop",
+      "listener_peak": 10,
+      "listeners": 4,
+      "listenurl": "http://example.com:8000/stream",
+      "server_description": "Description",
+      "server_name": "Station",
+      "server_type": "audio/mpeg",
+      "server_url": "https://example.com",
+      "stream_start_iso8601": "2024-05-01T12:01:00+0000",
+      "title": 1999,
+      "dummy": null
+    }
+  }
+}"#;
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_single_source() {
+    let status = IcecastStatus::from_json(SINGLE_SOURCE_JSON).unwrap();
+    assert_eq!(status.server_id(), Some("Icecast 2.4.4"));
+    assert_eq!(status.sources().len(), 1);
+
+    let source = status.source("stream").unwrap();
+    assert_eq!(source.mount(), Some("/stream"));
+    assert_eq!(source.listeners(), Some(4));
+    assert_eq!(source.title(), Some("1999"));
+
+    let headers = IcyHeaders::from(source);
+    assert_eq!(headers.name(), Some("Station"));
+    assert_eq!(headers.genre(), &["Rock", "Pop"]);
+    assert_eq!(headers.bitrate(), Some(128));
+    assert_eq!(headers.sample_rate(), Some(44100));
+    assert_eq!(headers.channels(), Some(2));
+
+    let metadata = IcyMetadata::from(source);
+    assert_eq!(metadata.stream_title(), Some("1999"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_multiple_sources() {
+    let json = r#"{"icestats": {"source": [
+        {"listenurl": "http://example.com/low", "title": "low title"},
+        {"listenurl": "http://example.com/high", "title": "Title", "artist": "Artist"}
+    ]}}"#;
+    let status = IcecastStatus::from_json(json).unwrap();
+    assert_eq!(status.sources().len(), 2);
+    let metadata = IcyMetadata::from(status.source("/high").unwrap());
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(metadata.title(), Some("Title"));
+    assert_eq!(metadata.stream_title(), Some("Artist - Title"));
+
+    let empty = IcecastStatus::from_json(r#"{"icestats": {"host": "example.com"}}"#).unwrap();
+    assert!(empty.sources().is_empty());
+    assert!(IcecastStatus::from_json("{}").is_err());
+}
+
+#[test]
+fn xml_stats() {
+    let xml = r#"<?xml version="1.0"?>
+<icestats>
+  <admin>admin@example.com</admin>
+  <host>example.com</host>
+  <server_id>Icecast 2.4.4</server_id>
+  <source mount="/stream.ogg">
+    <audio_info>ice-samplerate=48000;ice-bitrate=96;ice-channels=2</audio_info>
+    <genre>Jazz</genre>
+    <listeners>3</listeners>
+    <listenurl>http://example.com:8000/stream.ogg</listenurl>
+    <public>1</public>
+    <server_name>Station</server_name>
+    <server_type>application/ogg</server_type>
+    <title>Title &amp; More</title>
+    <artist>Artist</artist>
+  </source>
+  <source mount="/other">
+    <listeners>0</listeners>
+  </source>
+</icestats>"#;
+    let status = IcecastStatus::from_xml(xml).unwrap();
+    assert_eq!(status.host(), Some("example.com"));
+    assert_eq!(status.sources().len(), 2);
+    assert!(status.source("/missing").is_none());
+
+    let source = status.source("/stream.ogg").unwrap();
+    assert_eq!(source.public(), Some(true));
+    assert_eq!(source.server_type(), Some("application/ogg"));
+
+    let headers = IcyHeaders::from(source);
+    assert_eq!(headers.sample_rate(), Some(48000));
+    assert_eq!(headers.public(), Some(true));
+
+    let metadata = IcyMetadata::from(source);
+    assert_eq!(metadata.stream_title(), Some("Artist - Title & More"));
+    assert_eq!(status.source("other").unwrap().listeners(), Some(0));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn shoutcast_json_stats() {
+    let stats = ShoutcastStats::from_json(
+        r#"{
+  "currentlisteners": 4,
+  "peaklisteners": "10",
+  "maxlisteners": 100,
+  "uniquelisteners": 3,
+  "streamstatus": 1,
+  "servertitle": "Station",
+  "serverurl": "https://example.com",
+  "servergenre": "Rock",
+  "servergenre2": "Pop",
+  "servergenre3": "",
+  "songtitle": "Artist - Title",
+  "nexttitle": "Next Artist - Next Title",
+  "streampath": "/stream",
+  "content": "audio/mpeg",
+  "bitrate": "128",
+  "samplerate": "44100",
+  "version": "2.6.1.777 (posix(linux x64))"
+}"#,
+    )
+    .unwrap();
+    assert_eq!(stats.current_listeners(), Some(4));
+    assert_eq!(stats.peak_listeners(), Some(10));
+    assert_eq!(stats.stream_status(), Some(true));
+    assert_eq!(stats.genre(), ["Rock", "Pop"]);
+    assert_eq!(stats.content_type(), Some("audio/mpeg"));
+
+    let headers = IcyHeaders::from(&stats);
+    assert_eq!(headers.name(), Some("Station"));
+    assert_eq!(headers.genre(), ["Rock", "Pop"]);
+    assert_eq!(headers.bitrate(), Some(128));
+    assert_eq!(headers.sample_rate(), Some(44100));
+
+    let metadata = IcyMetadata::from(&stats);
+    assert_eq!(metadata.stream_title(), Some("Artist - Title"));
+    assert_eq!(
+        metadata.extension_titles(),
+        ["Artist - Title", "Next Artist - Next Title"]
+    );
+}
+
+#[test]
+fn shoutcast_xml_stats() {
+    let stats = ShoutcastStats::from_xml(
+        r#"<?xml version="1.0" standalone="yes" ?>
+<SHOUTCASTSERVER>
+  <CURRENTLISTENERS>4</CURRENTLISTENERS>
+  <STREAMSTATUS>0</STREAMSTATUS>
+  <SERVERTITLE>Station &amp; Friends</SERVERTITLE>
+  <SONGTITLE>Artist - Title</SONGTITLE>
+  <BITRATE>128</BITRATE>
+  <SONGHISTORY>
+    <SONG><PLAYEDAT>1714564860</PLAYEDAT><TITLE>Artist - Title</TITLE></SONG>
+    <SONG><PLAYEDAT>1714564800</PLAYEDAT><TITLE>Other - Song</TITLE></SONG>
+  </SONGHISTORY>
+</SHOUTCASTSERVER>"#,
+    )
+    .unwrap();
+    assert_eq!(stats.current_listeners(), Some(4));
+    assert_eq!(stats.stream_status(), Some(false));
+    assert_eq!(stats.server_title(), Some("Station & Friends"));
+    assert_eq!(stats.next_title(), None);
+    assert_eq!(
+        IcyMetadata::from(&stats).extension_titles(),
+        [] as [&str; 0]
+    );
+
+    let connected = SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_564_830);
+    let backfill: Vec<_> = stats.song_history().played_before(connected).collect();
+    assert_eq!(backfill.len(), 1);
+    assert_eq!(backfill[0].title(), "Other - Song");
+    assert_eq!(
+        IcyMetadata::from(backfill[0]).stream_title(),
+        Some("Other - Song")
+    );
+
+    assert!(ShoutcastStats::from_xml("<icestats></icestats>").is_err());
+
+    // Timestamps that don't fit in a SystemTime are skipped
+    let stats = ShoutcastStats::from_xml(
+        "<SHOUTCASTSERVER><SONGHISTORY><SONG><PLAYEDAT>18446744073709551615</\
+         PLAYEDAT><TITLE>Title</TITLE></SONG></SONGHISTORY></SHOUTCASTSERVER>",
+    )
+    .unwrap();
+    assert!(stats.song_history().songs().is_empty());
+}
+
+#[test]
+fn shoutcast_7_html() {
+    let stats = ShoutcastStats::from_7_html(
+        "<html><body>4,1,10,100,3,128,Artist, The - Title</body></html>",
+    )
+    .unwrap();
+    assert_eq!(stats.current_listeners(), Some(4));
+    assert_eq!(stats.stream_status(), Some(true));
+    assert_eq!(stats.max_listeners(), Some(100));
+    assert_eq!(stats.bitrate(), Some(128));
+    assert_eq!(stats.song_title(), Some("Artist, The - Title"));
+
+    assert!(ShoutcastStats::from_7_html("<html><body>4,1</body></html>").is_err());
+}
+
+#[test]
+fn shoutcast_played_html() {
+    let history = SongHistory::from_html(
+        r#"<html><body><table>
+<tr><td><b>Played @</b></td><td><b>Song Title</b></td></tr>
+<tr><td>12:01:00</td><td>Artist &amp; Band - Title<td><b>Current Song</b></td></tr>
+<TR><TD>11:57:30</TD><TD>Other - Song</TD></TR>
+</table></body></html>"#,
+    )
+    .unwrap();
+    let songs = history.songs();
+    assert_eq!(songs.len(), 2);
+    assert_eq!(songs[0].title(), "Artist & Band - Title");
+    assert_eq!(
+        songs[0].played_at(),
+        PlayedAt::TimeOfDay(Duration::from_secs(12 * 3600 + 60))
+    );
+    assert_eq!(songs[1].title(), "Other - Song");
+    // No full timestamps to compare against
+    assert_eq!(history.played_before(SystemTime::now()).count(), 0);
+
+    // 2024-05-01 00:00:00 UTC
+    let midnight = SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_521_600);
+    // Fetched at 12:05 on a server running at UTC+2
+    let fetched_at = midnight + Duration::from_secs(10 * 3600 + 5 * 60);
+    let resolved = history.with_timestamps(fetched_at, 2 * 3600);
+    assert_eq!(
+        resolved.songs()[0].played_at(),
+        PlayedAt::Timestamp(midnight + Duration::from_secs(10 * 3600 + 60))
+    );
+    let connected = midnight + Duration::from_secs(10 * 3600);
+    let backfill: Vec<_> = resolved.played_before(connected).collect();
+    assert_eq!(backfill.len(), 1);
+    assert_eq!(backfill[0].title(), "Other - Song");
+
+    // Times later in the day than the reference are from the previous day
+    assert_eq!(
+        PlayedAt::TimeOfDay(Duration::from_secs(23 * 3600 + 59 * 60))
+            .to_timestamp(midnight + Duration::from_secs(60), 0),
+        Some(midnight - Duration::from_secs(60))
+    );
+
+    assert!(SongHistory::from_html("<html><body>not found</body></html>").is_err());
+
+    let history = SongHistory::from_html(
+        "<table><tr><td>9999999999999999:00:00</td><td>Overflow</td></tr><tr><td>24:00:00</\
+         td><td>Hours</td></tr><tr><td>12:60:00</td><td>Minutes</td></tr><tr><td>12:00:60</\
+         td><td>Seconds</td></tr><tr><td>23:59:59</td><td>Valid</td></tr></table>",
+    )
+    .unwrap();
+    assert_eq!(history.songs().len(), 1);
+    assert_eq!(history.songs()[0].title(), "Valid");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn shoutcast_json_history() {
+    let history = SongHistory::from_json(
+        r#"[{"playedat":1714564860,"title":"Artist - Title","metadata":{}},{"playedat":1714564800,"title":"Other - Song"}]"#,
+    )
+    .unwrap();
+    assert_eq!(history.songs().len(), 2);
+    assert_eq!(
+        history.songs()[1].played_at(),
+        PlayedAt::Timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_564_800))
+    );
+
+    let history =
+        SongHistory::from_json(r#"[{"playedat":18446744073709551615,"title":"Title"}]"#).unwrap();
+    assert!(history.songs().is_empty());
+}
@@ -0,0 +1,75 @@
+use icy_metadata::IcyMetadata;
+use icy_metadata::stream_url::StreamUrlKind;
+use rstest::rstest;
+
+#[rstest]
+#[case("http://example.com/covers/123.JPG", StreamUrlKind::Artwork)]
+#[case("https://example.com/art.png?size=large", StreamUrlKind::Artwork)]
+#[case("https://example.com/cover.jpg?title=a", StreamUrlKind::Artwork)]
+#[case("https://example.com", StreamUrlKind::Website)]
+#[case("http://example.com/?page=home", StreamUrlKind::Website)]
+#[case("not a url", StreamUrlKind::Unknown)]
+#[case("foo=bar", StreamUrlKind::Unknown)]
+fn classify(#[case] stream_url: &str, #[case] kind: StreamUrlKind) {
+    assert_eq!(StreamUrlKind::parse(stream_url), kind);
+}
+
+#[test]
+fn query_string() {
+    let metadata = IcyMetadata::default().with_stream_url(
+        "artist=Some+Artist&title=Title%20%26%20More&album=Album&picture=x&cover=http%3A%2F%\
+         2Fexample.com%2Fa.jpg",
+    );
+    let kind = metadata.stream_url_kind().unwrap();
+    assert!(matches!(kind, StreamUrlKind::Query(_)));
+    let fields = kind.fields().unwrap();
+    assert_eq!(fields.artist(), Some("Some Artist"));
+    assert_eq!(fields.title(), Some("Title & More"));
+    assert_eq!(fields.album(), Some("Album"));
+    assert_eq!(fields.fields()["picture"], "x");
+    assert_eq!(metadata.artwork_url(), Some("http://example.com/a.jpg"));
+
+    // Track information in the query of a website link
+    let kind = StreamUrlKind::parse("https://example.com/now?Artist=A&Title=B");
+    assert_eq!(kind.fields().unwrap().title(), Some("B"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json() {
+    let metadata = IcyMetadata::default().with_stream_url(
+        r#"{"Artist": "Artist", "title": "Title", "year": 1999, "image": "https://example.com/a.png", "nested": {}}"#,
+    );
+    let kind = metadata.stream_url_kind().unwrap();
+    assert!(matches!(kind, StreamUrlKind::Json(_)));
+    let fields = kind.fields().unwrap();
+    assert_eq!(fields.artist(), Some("Artist"));
+    assert_eq!(fields.title(), Some("Title"));
+    assert_eq!(fields.fields()["year"], "1999");
+    assert!(!fields.fields().contains_key("nested"));
+    assert_eq!(metadata.artwork_url(), Some("https://example.com/a.png"));
+}
+
+#[test]
+fn artwork_url() {
+    let metadata = IcyMetadata::default().with_stream_url("http://example.com/a.jpg");
+    assert_eq!(metadata.artwork_url(), Some("http://example.com/a.jpg"));
+    assert_eq!(
+        IcyMetadata::default()
+            .with_stream_url("https://example.com")
+            .artwork_url(),
+        None
+    );
+    assert_eq!(
+        IcyMetadata::default()
+            .with_stream_url(" ")
+            .stream_url_kind(),
+        None
+    );
+
+    // Explicit artwork takes priority over the StreamUrl
+    let metadata: IcyMetadata = r#"StreamTitle='Artist - text="Title" song_spot="M" amgArtworkURL="http://example.com/b.jpg"';StreamUrl='http://example.com/a.jpg';"#
+        .parse()
+        .unwrap();
+    assert_eq!(metadata.artwork_url(), Some("http://example.com/b.jpg"));
+}
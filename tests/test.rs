@@ -1,12 +1,16 @@
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 use std::num::NonZeroUsize;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use http::HeaderMap;
 use icy_metadata::error::{EmptyMetadataError, MetadataParseError};
 use icy_metadata::{IcyHeaders, IcyMetadata, IcyMetadataReader, add_icy_metadata_header};
 use rstest::rstest;
 
+use crate::common::ShortReader;
+
+mod common;
+
 #[test]
 fn read_headers() {
     let mut headers = HeaderMap::new();
@@ -416,6 +420,105 @@ fn seek_from_start_to_future(
     }
 }
 
+#[rstest]
+fn tee_raw_data(
+    #[values("StreamTitle='title{}';", "")] meta_bytes: &str,
+    #[values((10,5), (1,0))] byte_lens: (usize, usize),
+    #[values(1, 3)] read_size: usize,
+) {
+    let (meta_int, trailing_bytes) = byte_lens;
+    let mut data = Vec::new();
+    let (reader, metadata) =
+        setup_data_template(meta_bytes, meta_int, &mut data, 2, trailing_bytes);
+    // The reader borrows the stream data, so generate a second copy to compare against
+    let mut expected = Vec::new();
+    setup_data_template(meta_bytes, meta_int, &mut expected, 2, trailing_bytes);
+    let raw = SharedBuffer::default();
+    let mut reader = reader.tee(raw.clone());
+
+    let mut audio = Vec::new();
+    let mut buf = vec![0; read_size];
+    loop {
+        let read = reader.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        audio.extend_from_slice(&buf[..read]);
+        let raw = raw.0.lock().unwrap();
+        assert!(expected.starts_with(&raw));
+    }
+    assert_eq!(audio, vec![1; meta_int * 2 + trailing_bytes]);
+    assert_eq!(*raw.0.lock().unwrap(), expected);
+    assert_eq!(metadata.read().unwrap().len(), 2);
+    assert!(reader.seek(SeekFrom::Start(0)).is_err());
+}
+
+#[rstest]
+fn short_inner_reads(#[values(1, 512, 1000, 4096)] read_size: usize) {
+    let meta_int = 1000;
+    let mut data = Vec::new();
+    let mut expected = Vec::new();
+    for i in 0..3 {
+        let audio: Vec<_> = (0..meta_int).map(|j| (j % 251) as u8 ^ i).collect();
+        data.extend_from_slice(&audio);
+        expected.extend(audio);
+        let mut meta = format!("StreamTitle='title{i}';").into_bytes();
+        let blocks = meta.len().div_ceil(16);
+        meta.resize(blocks * 16, 0);
+        data.push(blocks as u8);
+        data.extend(meta);
+    }
+    data.extend(vec![7; 300]);
+    expected.extend(vec![7; 300]);
+
+    let metadata = Arc::new(RwLock::new(vec![]));
+    let mut reader = {
+        let metadata = metadata.clone();
+        IcyMetadataReader::new(
+            ShortReader {
+                inner: Cursor::new(data),
+                limit: 100,
+            },
+            NonZeroUsize::new(meta_int),
+            move |meta| {
+                metadata.write().unwrap().push(meta);
+            },
+        )
+    };
+    let mut audio = Vec::new();
+    let mut buf = vec![0; read_size];
+    loop {
+        let read = reader.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        audio.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(audio, expected);
+
+    let metadata = metadata.read().unwrap();
+    assert_eq!(metadata.len(), 3);
+    for (i, metadata) in metadata.iter().enumerate() {
+        assert_eq!(
+            metadata.clone().unwrap().stream_title().unwrap(),
+            format!("title{i}")
+        );
+    }
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 enum MetadataSetup<'a> {
     Template { val: &'a str, iters: usize },
     List(Vec<&'a str>),
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use icy_metadata::IcyMetadata;
+use icy_metadata::timeline::MetadataTimeline;
+
+fn timeline() -> MetadataTimeline {
+    let mut timeline = MetadataTimeline::new();
+    timeline.push(
+        Duration::from_millis(205_500),
+        IcyMetadata::default().with_stream_title("Rock & <Roll>"),
+    );
+    timeline.push(
+        Duration::ZERO,
+        IcyMetadata::default()
+            .with_stream_title("Artist - Title")
+            .with_stream_url("http://example.com"),
+    );
+    timeline
+}
+
+#[test]
+fn webvtt() {
+    assert_eq!(
+        timeline().to_webvtt(Duration::from_secs(3723)),
+        "WEBVTT
+
+1
+00:00:00.000 --> 00:03:25.500
+Artist - Title
+
+2
+00:03:25.500 --> 01:02:03.000
+Rock &amp; &lt;Roll&gt;
+"
+    );
+}
+
+#[test]
+fn line_breaks() {
+    let mut timeline = MetadataTimeline::new();
+    timeline.push(
+        Duration::ZERO,
+        IcyMetadata::default().with_stream_title("Artist\r\n\nTitle"),
+    );
+    assert_eq!(
+        timeline.to_webvtt(Duration::from_secs(1)),
+        "WEBVTT
+
+1
+00:00:00.000 --> 00:00:01.000
+Artist   Title
+"
+    );
+    assert_eq!(timeline.to_lrc(), "[00:00.00]Artist   Title\n");
+}
+
+#[test]
+fn lrc() {
+    assert_eq!(
+        timeline().to_lrc(),
+        "[00:00.00]Artist - Title\n[03:25.50]Rock & <Roll>\n"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_lines() {
+    let timeline = timeline();
+    let mut output = Vec::new();
+    timeline.write_json_lines(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 2);
+    assert!(output.starts_with(r#"{"time":0.0,"metadata":{"stream_title":"Artist - Title""#));
+    assert!(output.contains(r#""stream_url":"http://example.com""#));
+
+    let imported = MetadataTimeline::read_json_lines(format!("{output}\n").as_bytes()).unwrap();
+    assert_eq!(imported, timeline);
+    assert_eq!(imported.entries()[1].time(), Duration::from_millis(205_500));
+
+    let error = MetadataTimeline::read_json_lines(r#"{"time":-1,"metadata":{}}"#.as_bytes());
+    assert!(error.unwrap_err().to_string().contains("line 1"));
+}
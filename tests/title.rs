@@ -0,0 +1,105 @@
+use icy_metadata::IcyMetadata;
+use icy_metadata::title::{TitleOrder, TitleParser};
+use rstest::rstest;
+
+#[rstest]
+#[case("Artist - Title", Some("Artist"), Some("Title"), false)]
+#[case("Artist \u{2013} Title", Some("Artist"), Some("Title"), false)]
+#[case("Artist / Title", Some("Artist"), Some("Title"), false)]
+#[case("Artist|Title", Some("Artist"), Some("Title"), false)]
+#[case("Jay-Z - Run This Town", Some("Jay-Z"), Some("Run This Town"), false)]
+#[case("AC/DC - Back In Black", Some("AC/DC"), Some("Back In Black"), false)]
+#[case("A-ha", None, Some("A-ha"), false)]
+#[case("Title by Artist", None, Some("Title by Artist"), false)]
+#[case("Stand By Me", None, Some("Stand By Me"), false)]
+#[case("Stand by Me", None, Some("Stand by Me"), false)]
+#[case("Artist - Title - Live", Some("Artist"), Some("Title - Live"), true)]
+#[case("Artist - Title / Live", Some("Artist"), Some("Title / Live"), true)]
+#[case("Artist - Title | Live", Some("Artist"), Some("Title | Live"), true)]
+#[case("  Artist -  ", Some("Artist"), None, false)]
+#[case("", None, None, false)]
+fn split_title(
+    #[case] stream_title: &str,
+    #[case] artist: Option<&str>,
+    #[case] title: Option<&str>,
+    #[case] ambiguous: bool,
+) {
+    let parsed = TitleParser::new().parse(stream_title);
+    assert_eq!(parsed.artist(), artist);
+    assert_eq!(parsed.title(), title);
+    assert_eq!(parsed.is_ambiguous(), ambiguous);
+}
+
+#[rstest]
+#[case("Title by Artist", Some("Artist"), Some("Title"))]
+#[case("Stand by Me", Some("Me"), Some("Stand"))]
+#[case(
+    "Written by Someone by Artist",
+    Some("Artist"),
+    Some("Written by Someone")
+)]
+#[case("Artist - Title by Someone", Some("Artist"), Some("Title by Someone"))]
+fn split_by(#[case] stream_title: &str, #[case] artist: Option<&str>, #[case] title: Option<&str>) {
+    let parsed = TitleParser::new().parse_by(true).parse(stream_title);
+    assert_eq!(parsed.artist(), artist);
+    assert_eq!(parsed.title(), title);
+    // A separator takes precedence, otherwise the split is a guess
+    assert_eq!(parsed.is_ambiguous(), !stream_title.contains(" - "));
+}
+
+#[rstest]
+#[case("Artist feat. A & B - Title", &["A", "B"])]
+#[case("Artist - Title (ft. A, B) [Remix]", &["A", "B"])]
+#[case("Artist Featuring Guest (Live) - Title", &["Guest"])]
+#[case("Featherstone - Shift", &[])]
+fn featured_artists(#[case] stream_title: &str, #[case] featured: &[&str]) {
+    assert_eq!(TitleParser::new().parse(stream_title).featured(), featured);
+}
+
+#[test]
+fn custom_parser() {
+    let parser = TitleParser::new()
+        .separators([" ~ "])
+        .order(TitleOrder::TitleArtist)
+        .parse_by(false);
+    let parsed = parser.parse("Title ~ Artist");
+    assert_eq!(parsed.artist(), Some("Artist"));
+    assert_eq!(parsed.title(), Some("Title"));
+    assert_eq!(parser.parse("Title by Artist").artist(), None);
+    assert_eq!(parser.parse("Artist - Title").artist(), None);
+
+    // Overlapping separators only count once
+    let parser = TitleParser::new().separators([" - ", "-"]);
+    assert!(!parser.parse("Artist - Title").is_ambiguous());
+    assert!(parser.parse("Jay-Z - Title").is_ambiguous());
+}
+
+#[test]
+fn station_overrides() {
+    let parser = TitleParser::new()
+        .station_override("Station", TitleParser::new().order(TitleOrder::TitleArtist));
+    let metadata = IcyMetadata::default().with_stream_title("Title - Artist");
+    let parsed = metadata.parse_title(parser.for_station(Some("Station")));
+    assert_eq!(parsed.artist(), Some("Artist"));
+    let parsed = metadata.parse_title(parser.for_station(Some("Other")));
+    assert_eq!(parsed.artist(), Some("Title"));
+    assert_eq!(parser.for_station(None), &parser);
+}
+
+#[test]
+fn metadata_fallback() {
+    let metadata = IcyMetadata::default().with_stream_title("Artist - Title");
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(metadata.title(), Some("Title"));
+    assert_eq!(IcyMetadata::default().title(), None);
+
+    // Structured values aren't parsed
+    let metadata = IcyMetadata::from_xml(
+        "<metadata><TIT2>Title - Part 2</TIT2><TPE1>Artist</TPE1></metadata>",
+    )
+    .unwrap();
+    let parsed = metadata.parse_title(&TitleParser::new());
+    assert_eq!(parsed.artist(), Some("Artist"));
+    assert_eq!(parsed.title(), Some("Title - Part 2"));
+    assert!(!parsed.is_ambiguous());
+}
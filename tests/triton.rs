@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use icy_metadata::IcyMetadata;
+use icy_metadata::triton::{SpotType, TritonTitle};
+
+const METADATA: &str = r#"StreamTitle='Artist - text="Song "Live"" song_spot="M" MediaBaseId="2386515" itunesTrackId="0" amgArtworkURL="http://example.com/art.jpg" length="00:03:25" spotInstanceId="-1"';StreamUrl='';"#;
+
+#[test]
+fn parse_metadata() {
+    let metadata: IcyMetadata = METADATA.parse().unwrap();
+    assert_eq!(metadata.stream_title(), Some("Artist - Song \"Live\""));
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(metadata.title(), Some("Song \"Live\""));
+    assert_eq!(metadata.artwork_urls(), ["http://example.com/art.jpg"]);
+
+    let triton = metadata.triton().unwrap();
+    assert_eq!(
+        triton.raw(),
+        r#"Artist - text="Song "Live"" song_spot="M" MediaBaseId="2386515" itunesTrackId="0" amgArtworkURL="http://example.com/art.jpg" length="00:03:25" spotInstanceId="-1""#
+    );
+    assert_eq!(triton.spot_type(), Some(SpotType::Music));
+    assert!(triton.spot_type().unwrap().is_music());
+    assert_eq!(triton.duration(), Some(Duration::from_secs(205)));
+    assert_eq!(triton.attribute("mediabaseid"), Some("2386515"));
+    assert_eq!(triton.attributes()["spotInstanceId"], "-1");
+}
+
+#[test]
+fn parse_spot() {
+    let triton =
+        TritonTitle::parse(r#"text="" song_spot="F" length="30" amgArtworkURL="""#).unwrap();
+    assert_eq!(triton.artist(), None);
+    assert_eq!(triton.title(), None);
+    assert_eq!(triton.spot_type(), Some(SpotType::Spot));
+    assert_eq!(triton.artwork_url(), None);
+    assert_eq!(triton.duration(), Some(Duration::from_secs(30)));
+
+    // Nothing to replace the original title with
+    let metadata: IcyMetadata = r#"StreamTitle='text="" song_spot="F"';"#.parse().unwrap();
+    assert_eq!(metadata.stream_title(), Some(r#"text="" song_spot="F""#));
+    assert_eq!(metadata.artist(), None);
+    assert_eq!(metadata.title(), None);
+    assert!(!metadata.triton().unwrap().spot_type().unwrap().is_music());
+
+    let metadata: IcyMetadata =
+        r#"StreamTitle='song_spot="T" length="00:00:15"';"#.parse().unwrap();
+    assert_eq!(
+        metadata.stream_title(),
+        Some(r#"song_spot="T" length="00:00:15""#)
+    );
+    assert_eq!(
+        metadata.triton().unwrap().duration(),
+        Some(Duration::from_secs(15))
+    );
+}
+
+#[test]
+fn plain_titles() {
+    assert_eq!(TritonTitle::parse("Artist - Title"), None);
+    assert_eq!(TritonTitle::parse(r#"Artist - Title="Quoted""#), None);
+    let metadata: IcyMetadata = "StreamTitle='Artist - Title';".parse().unwrap();
+    assert_eq!(metadata.triton(), None);
+    assert_eq!(metadata.stream_title(), Some("Artist - Title"));
+}
@@ -0,0 +1,93 @@
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+
+use icy_metadata::error::MetadataParseError;
+use icy_metadata::ultravox::UltravoxReader;
+use icy_metadata::{ArtworkKind, IcyMetadata};
+
+fn message(class_type: u16, payload: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x5A, 0x00];
+    data.extend_from_slice(&class_type.to_be_bytes());
+    data.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    data.extend_from_slice(payload);
+    data.push(0x00);
+    data
+}
+
+fn metadata_message(class_type: u16, id: u16, span: u16, index: u16, data: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&id.to_be_bytes());
+    payload.extend_from_slice(&span.to_be_bytes());
+    payload.extend_from_slice(&index.to_be_bytes());
+    payload.extend_from_slice(data);
+    message(class_type, &payload)
+}
+
+type MetadataLock = Arc<RwLock<Vec<Result<IcyMetadata, MetadataParseError>>>>;
+
+fn read_all(data: Vec<u8>) -> (Vec<u8>, MetadataLock) {
+    let metadata = Arc::new(RwLock::new(Vec::new()));
+    let mut reader = {
+        let metadata = metadata.clone();
+        UltravoxReader::new(data.as_slice(), move |meta| {
+            metadata.write().unwrap().push(meta);
+        })
+    };
+    let mut audio = Vec::new();
+    reader.read_to_end(&mut audio).unwrap();
+    (audio, metadata)
+}
+
+#[test]
+fn audio_and_multipart_xml() {
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\" \
+               ?><metadata><TIT2>Title</TIT2><TPE1>Artist</TPE1></metadata>";
+    let (first, second) = xml.as_bytes().split_at(20);
+
+    let mut data = message(0x7000, &[1, 2, 3]);
+    data.extend(metadata_message(0x3902, 1, 2, 1, first));
+    data.extend(message(0x8001, &[4, 5]));
+    data.extend(metadata_message(0x3902, 1, 2, 2, second));
+    data.extend(message(0x7000, &[6]));
+
+    let (audio, metadata) = read_all(data);
+    assert_eq!(audio, vec![1, 2, 3, 4, 5, 6]);
+    let metadata = metadata.read().unwrap();
+    assert_eq!(metadata.len(), 1);
+    assert_eq!(
+        metadata[0].as_ref().unwrap().stream_title(),
+        Some("Artist - Title")
+    );
+}
+
+#[test]
+fn artwork() {
+    let mut data = metadata_message(0x5001, 1, 1, 1, &[0x89, b'P', b'N', b'G']);
+    data.extend(metadata_message(0x4000, 2, 1, 1, &[0xFF, 0xD8]));
+    // Empty artwork clears the image and shouldn't produce any metadata
+    data.extend(metadata_message(0x5000, 3, 1, 1, &[]));
+    data.extend(message(0x7000, &[1]));
+
+    let (audio, metadata) = read_all(data);
+    assert_eq!(audio, vec![1]);
+    let metadata = metadata.read().unwrap();
+    assert_eq!(metadata.len(), 2);
+
+    let track_art = &metadata[0].as_ref().unwrap().artwork()[0];
+    assert_eq!(track_art.kind(), ArtworkKind::Track);
+    assert_eq!(track_art.mime_type(), "image/png");
+    assert_eq!(track_art.data(), &[0x89, b'P', b'N', b'G']);
+
+    let station_art = &metadata[1].as_ref().unwrap().artwork()[0];
+    assert_eq!(station_art.kind(), ArtworkKind::Station);
+    assert_eq!(station_art.mime_type(), "image/jpeg");
+}
+
+#[test]
+fn resync_after_garbage() {
+    let mut data = vec![0x01, 0x02];
+    data.extend(message(0x7000, &[1, 2]));
+
+    let (audio, _) = read_all(data);
+    assert_eq!(audio, vec![1, 2]);
+}
@@ -0,0 +1,72 @@
+use icy_metadata::IcyMetadata;
+use icy_metadata::error::MetadataParseError;
+
+#[test]
+fn full_xml() {
+    let metadata = IcyMetadata::from_xml(
+        r#"<?xml version="1.0" encoding="UTF-8" ?>
+<metadata>
+  <TIT2>Title &amp; More</TIT2>
+  <TPE1>Artist</TPE1>
+  <TALB>Album</TALB>
+  <TCON>Rock</TCON>
+  <APIC url="http://art/track.jpg" />
+  <extension>
+    <title seq="2">Next Artist - Next Title</title>
+    <title seq="1">Artist - Title &amp; More</title>
+    <soon>Next Title</soon>
+    <DJ>Host</DJ>
+    <stationart>http://art/station.png</stationart>
+  </extension>
+</metadata>"#,
+    )
+    .unwrap();
+
+    assert_eq!(metadata.title(), Some("Title & More"));
+    assert_eq!(metadata.artist(), Some("Artist"));
+    assert_eq!(metadata.album(), Some("Album"));
+    assert_eq!(metadata.stream_title(), Some("Artist - Title & More"));
+    assert_eq!(
+        metadata.extension_titles(),
+        &["Artist - Title & More", "Next Artist - Next Title"]
+    );
+    assert_eq!(
+        metadata.artwork_urls(),
+        &["http://art/track.jpg", "http://art/station.png"]
+    );
+    assert_eq!(metadata.custom_fields().get("TCON").unwrap(), "Rock");
+    assert_eq!(metadata.custom_fields().get("soon").unwrap(), "Next Title");
+    assert_eq!(metadata.custom_fields().get("DJ").unwrap(), "Host");
+}
+
+#[test]
+fn stream_title_from_id3_fields() {
+    let metadata =
+        IcyMetadata::from_xml("<metadata><TIT2>Title</TIT2><TPE1>Artist</TPE1></metadata>")
+            .unwrap();
+    assert_eq!(metadata.stream_title(), Some("Artist - Title"));
+    assert!(metadata.extension_titles().is_empty());
+
+    let metadata = IcyMetadata::from_xml("<metadata><TPE1>Artist</TPE1></metadata>").unwrap();
+    assert_eq!(metadata.stream_title(), Some("Artist"));
+}
+
+#[test]
+fn legacy_fallback() {
+    let metadata = IcyMetadata::from_xml("StreamTitle='Artist - Title';").unwrap();
+    assert_eq!(metadata.stream_title(), Some("Artist - Title"));
+    assert_eq!(metadata.title(), Some("Title"));
+    assert_eq!(metadata.artist(), Some("Artist"));
+}
+
+#[test]
+fn empty_xml() {
+    assert!(matches!(
+        IcyMetadata::from_xml("<metadata></metadata>"),
+        Err(MetadataParseError::Empty(_))
+    ));
+    assert!(matches!(
+        IcyMetadata::from_xml("<metadata><TIT2>unclosed</metadata"),
+        Err(MetadataParseError::InvalidXml(_))
+    ));
+}
@@ -0,0 +1,196 @@
+use std::io::BufReader;
+
+use icy_metadata::yp::YpReader;
+use icy_metadata::{IcyHeaders, IcyMetadata};
+
+const DIRECTORY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<directory>
+  <entry>
+    <server_name>Station &amp; Friends</server_name>
+    <listen_url>http://example.com:8000/stream</listen_url>
+    <server_type>audio/mpeg</server_type>
+    <bitrate>128</bitrate>
+    <channels>2</channels>
+    <samplerate>44100</samplerate>
+    <genre>Rock Pop</genre>
+    <current_song>Artist - Title</current_song>
+  </entry>
+  <entry>
+    <server_name>Other</server_name>
+    <listen_url>http://example.org/radio.ogg</listen_url>
+    <server_type>application/ogg</server_type>
+    <bitrate>Quality 5.00</bitrate>
+    <genre />
+  </entry>
+</directory>"#;
+
+#[test]
+fn read_entries() {
+    // Use a small buffer to make sure entries are read incrementally
+    let reader = YpReader::new(BufReader::with_capacity(16, DIRECTORY.as_bytes()));
+    let entries: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let entry = &entries[0];
+    assert_eq!(entry.server_name(), Some("Station & Friends"));
+    assert_eq!(entry.listen_url(), Some("http://example.com:8000/stream"));
+    assert_eq!(entry.server_type(), Some("audio/mpeg"));
+    assert_eq!(entry.genre(), ["Rock", "Pop"]);
+
+    let headers = IcyHeaders::from(entry);
+    assert_eq!(headers.name(), Some("Station & Friends"));
+    assert_eq!(headers.bitrate(), Some(128));
+    assert_eq!(headers.sample_rate(), Some(44100));
+    assert_eq!(headers.channels(), Some(2));
+    assert_eq!(
+        IcyMetadata::from(entry).stream_title(),
+        Some("Artist - Title")
+    );
+
+    assert_eq!(entries[1].bitrate(), None);
+    assert!(entries[1].genre().is_empty());
+    assert_eq!(IcyMetadata::from(&entries[1]).stream_title(), None);
+}
+
+#[test]
+fn invalid_document() {
+    let mut reader = YpReader::new(
+        "<directory><entry><server_name>Station</server_name></entry><entry>".as_bytes(),
+    );
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+}
+
+#[cfg(feature = "reqwest")]
+mod client {
+    use std::time::Duration;
+
+    use icy_metadata::error::YpError;
+    use icy_metadata::yp::YpClient;
+    use icy_metadata::{IcyHeaders, IcyMetadata};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Mock YP endpoint that sends each response to a new connection and returns the request
+    /// bodies.
+    async fn serve(responses: Vec<&'static str>) -> (u16, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.split_once(':') {
+                        if key.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).await.unwrap();
+                stream.write_all(response.as_bytes()).await.unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+            }
+            bodies
+        });
+        (port, handle)
+    }
+
+    fn client(port: u16) -> YpClient {
+        YpClient::new(
+            format!("http://127.0.0.1:{port}/cgi-bin/yp-cgi")
+                .parse()
+                .unwrap(),
+            "http://example.com:8000/stream",
+        )
+    }
+
+    #[test]
+    fn add_form() {
+        let headers = IcyHeaders::default()
+            .with_name("Station & Friends")
+            .with_genre(["Rock", "Pop"])
+            .with_bitrate(128)
+            .with_sample_rate(44100)
+            .with_channels(2);
+        let metadata = IcyMetadata::default().with_stream_title("Artist - Title");
+        let form = client(8000)
+            .content_type("audio/aac")
+            .add_form(&headers, Some(&metadata));
+        let field = |name| {
+            form.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(field("action"), Some("add"));
+        assert_eq!(field("sn"), Some("Station & Friends"));
+        assert_eq!(field("genre"), Some("Rock Pop"));
+        assert_eq!(field("listenurl"), Some("http://example.com:8000/stream"));
+        assert_eq!(field("type"), Some("audio/aac"));
+        assert_eq!(field("b"), Some("128"));
+        assert_eq!(field("channels"), Some("2"));
+        assert_eq!(field("samplerate"), Some("44100"));
+        assert_eq!(field("st"), Some("Artist - Title"));
+        assert_eq!(field("quality"), None);
+    }
+
+    #[tokio::test]
+    async fn add_touch_remove() {
+        let (port, server) = serve(vec![
+            "HTTP/1.0 200 OK\r\nYPResponse: 1\r\nYPMessage: Successfully added\r\nSID: \
+             abc123\r\nTouchFreq: 200\r\nConnection: close\r\n\r\n",
+            "HTTP/1.0 200 OK\r\nYPResponse: 1\r\nConnection: close\r\n\r\n",
+            "HTTP/1.0 200 OK\r\nYPResponse: 1\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let mut client = client(port);
+        let metadata = IcyMetadata::default().with_stream_title("Artist - Title");
+        assert!(matches!(
+            client.touch(&metadata, 1).await,
+            Err(YpError::NotListed)
+        ));
+
+        let response = client
+            .add(&IcyHeaders::default().with_name("Station"), None)
+            .await
+            .unwrap();
+        assert_eq!(response.message(), Some("Successfully added"));
+        assert_eq!(client.sid(), Some("abc123"));
+        assert_eq!(client.touch_frequency(), Some(Duration::from_secs(200)));
+
+        client.touch(&metadata, 4).await.unwrap();
+        client.remove().await.unwrap();
+        assert_eq!(client.sid(), None);
+
+        let bodies = server.await.unwrap();
+        assert!(bodies[0].starts_with("action=add&sn=Station&"));
+        assert_eq!(
+            bodies[1],
+            "action=touch&sid=abc123&st=Artist%20-%20Title&listeners=4"
+        );
+        assert_eq!(bodies[2], "action=remove&sid=abc123");
+    }
+
+    #[tokio::test]
+    async fn add_rejected() {
+        let (port, _server) = serve(vec![
+            "HTTP/1.0 200 OK\r\nYPResponse: 0\r\nYPMessage: Bad listen URL\r\nConnection: \
+             close\r\n\r\n",
+        ])
+        .await;
+        let mut client = client(port);
+        let res = client.add(&IcyHeaders::default(), None).await;
+        assert!(matches!(res, Err(YpError::Rejected(message)) if message == "Bad listen URL"));
+        assert_eq!(client.sid(), None);
+    }
+}